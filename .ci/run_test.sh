@@ -14,3 +14,4 @@ if cargo clippy --version >/dev/null 2>&1; then
 fi
 
 cargo test --all
+cargo test --all-features
@@ -56,6 +56,7 @@ pub fn ExtractPath(input: TokenStream) -> TokenStream {
     };
 
     let path_value = path.value();
+    let template = path_value.trim();
     let components = match parse_path(&path_value, &path) {
         Ok(components) => components,
         Err(err) => return err.to_compile_error().into(),
@@ -146,6 +147,10 @@ pub fn ExtractPath(input: TokenStream) -> TokenStream {
                 #(#extracts)*
                 Ok((#(#output_idents,)*))
             }
+
+            fn template() -> Option<&'static str> {
+                Some(#template)
+            }
         }
     })
 }
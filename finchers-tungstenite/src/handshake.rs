@@ -1,10 +1,10 @@
 //! The implementation of WebSocket handshake process.
 
 use {
-    failure::Fail,
     finchers::error::HttpError,
     http::{header, Request, StatusCode},
     sha1::Sha1,
+    std::{error::Error as StdError, fmt},
 };
 
 #[derive(Debug)]
@@ -64,12 +64,19 @@ pub(crate) fn handshake(request: &Request<()>) -> Result<Accept, HandshakeError>
 }
 
 /// The error type during handling WebSocket handshake.
-#[derive(Debug, Fail)]
-#[fail(display = "handshake error: {}", kind)]
+#[derive(Debug)]
 pub struct HandshakeError {
     kind: HandshakeErrorKind,
 }
 
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "handshake error: {}", self.kind)
+    }
+}
+
+impl StdError for HandshakeError {}
+
 impl From<HandshakeErrorKind> for HandshakeError {
     fn from(kind: HandshakeErrorKind) -> Self {
         HandshakeError { kind }
@@ -90,18 +97,28 @@ impl HandshakeError {
 }
 
 #[allow(missing_docs)]
-#[derive(Debug, Fail)]
+#[derive(Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum HandshakeErrorKind {
-    #[fail(display = "missing header: `{}'", name)]
     MissingHeader { name: &'static str },
-
-    #[fail(display = "The header value is invalid: `{}'", name)]
     InvalidHeader { name: &'static str },
-
-    #[fail(display = "The value of `Sec-WebSocket-Key` is invalid")]
     InvalidSecWebSocketKey,
-
-    #[fail(display = "The value of `Sec-WebSocket-Version` must be equal to '13'")]
     InvalidSecWebSocketVersion,
 }
+
+impl fmt::Display for HandshakeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeErrorKind::MissingHeader { name } => write!(f, "missing header: `{}'", name),
+            HandshakeErrorKind::InvalidHeader { name } => {
+                write!(f, "The header value is invalid: `{}'", name)
+            }
+            HandshakeErrorKind::InvalidSecWebSocketKey => {
+                f.write_str("The value of `Sec-WebSocket-Key` is invalid")
+            }
+            HandshakeErrorKind::InvalidSecWebSocketVersion => {
+                f.write_str("The value of `Sec-WebSocket-Version` must be equal to '13'")
+            }
+        }
+    }
+}
@@ -8,6 +8,7 @@ use {
         service::Context,
     },
     futures::{Future, Poll},
+    http::Method,
     std::{marker::PhantomData, rc::Rc},
 };
 
@@ -144,6 +145,8 @@ where
 pub struct PreflightContext<'a> {
     context: &'a Context,
     cursor: CursorInner,
+    route: Vec<&'static str>,
+    method: Option<Method>,
     _anchor: PhantomData<Rc<()>>,
 }
 
@@ -153,6 +156,8 @@ impl<'a> PreflightContext<'a> {
         PreflightContext {
             context,
             cursor: CursorInner { pos: 1, popped: 0 },
+            route: vec![],
+            method: None,
             _anchor: PhantomData,
         }
     }
@@ -163,6 +168,46 @@ impl<'a> PreflightContext<'a> {
         &*self.context
     }
 
+    /// Returns the HTTP method used by the routing.
+    ///
+    /// This is the method of the request, except while a CORS preflight request
+    /// is being matched against the method requested by the client.
+    #[inline]
+    pub fn method(&self) -> &Method {
+        self.method
+            .as_ref()
+            .unwrap_or_else(|| self.context.method())
+    }
+
+    /// Overrides the HTTP method used by the routing.
+    pub(crate) fn set_method(&mut self, method: Option<Method>) {
+        self.method = method;
+    }
+
+    /// Appends a path template matched to the request.
+    ///
+    /// Since the route is stored in this context, the templates recorded by the
+    /// branches abandoned by the routing are discarded together with the cursor.
+    pub(crate) fn push_route(&mut self, template: &'static str) {
+        self.route.push(template);
+    }
+
+    /// Concatenates the recorded templates into a route template.
+    pub(crate) fn into_route(self) -> Option<String> {
+        if self.route.is_empty() {
+            return None;
+        }
+        let mut route: String = self
+            .route
+            .iter()
+            .map(|template| template.trim_end_matches('/'))
+            .collect();
+        if route.is_empty() {
+            route.push('/');
+        }
+        Some(route)
+    }
+
     /// Creates a `Cursor` to traverse the path segments.
     #[inline]
     pub fn cursor(&mut self) -> Cursor<'_> {
@@ -2,25 +2,48 @@
 
 mod and;
 mod and_then;
+#[cfg(feature = "compression")]
+mod compress;
+mod concurrency_limit;
+mod cors;
+#[cfg(feature = "tracing")]
+mod instrument;
 mod map;
 mod map_err;
 mod or;
 mod or_strict;
+mod rate_limit;
 mod recover;
 
 pub use self::{
     and::And, //
     and_then::AndThen,
+    concurrency_limit::ConcurrencyLimit,
+    cors::WithCors,
     map::Map,
     map_err::MapErr,
     or::Or,
     or_strict::OrStrict,
+    rate_limit::RateLimit,
     recover::Recover,
 };
 
+#[cfg(feature = "compression")]
+pub use self::compress::Compress;
+#[cfg(feature = "tracing")]
+pub use self::instrument::Instrument;
+
 use {
     super::IsEndpoint, //
-    crate::error::{Error, HttpError},
+    crate::{
+        endpoints::rate_limit::{KeyExtractor, MemoryStore, Policy, Store},
+        error::{Error, HttpError},
+        service::{
+            cors::Cors,
+            load_shed::{self, Semaphore},
+        },
+    },
+    std::{fmt, sync::Arc},
 };
 
 /// A set of extension methods for combining the multiple endpoints.
@@ -83,6 +106,97 @@ pub trait EndpointExt: IsEndpoint + Sized {
     fn recover<F>(self, f: F) -> Recover<Self, F> {
         Recover { endpoint: self, f }
     }
+
+    /// Create an endpoint which limits the number of requests handled concurrently by `self`.
+    ///
+    /// The permit is acquired when the request is matched to this endpoint and the
+    /// action has not been completed in the preflight, and is held until the response
+    /// is created. The excess requests are rejected immediately
+    /// with `503 Service Unavailable` (see `service::load_shed::Overloaded`), whose
+    /// `Retry-After` is 1 second unless changed by `ConcurrencyLimit::retry_after`.
+    ///
+    /// The clones of the returned endpoint share the same limit.
+    fn concurrency_limit(self, max: usize) -> ConcurrencyLimit<Self> {
+        ConcurrencyLimit {
+            endpoint: self,
+            semaphore: Arc::new(Semaphore::new(max)),
+            retry_after: load_shed::default_retry_after(),
+        }
+    }
+
+    /// Create an endpoint which restricts the number of requests to `self` for each key.
+    ///
+    /// The quota of the key extracted from the request is consumed when `self` is
+    /// chosen by the routing, and the requests exceeding the limit are rejected with
+    /// `429 Too Many Requests`. The current quota is reported to the clients by the
+    /// `RateLimit-*` response headers. See the documentation of `endpoints::rate_limit`
+    /// for details.
+    ///
+    /// The state is kept in a `MemoryStore` shared by the clones of the returned endpoint.
+    /// Use `rate_limit_with_store` in order to keep the state in another store.
+    fn rate_limit<K>(self, key: K, policy: Policy) -> RateLimit<Self, K>
+    where
+        K: KeyExtractor,
+    {
+        self.rate_limit_with_store(key, policy, MemoryStore::new())
+    }
+
+    /// Create an endpoint which restricts the number of requests to `self` for each key,
+    /// keeping the state in the specified store.
+    ///
+    /// The store is shared by the clones of the returned endpoint. See also the
+    /// documentation of `rate_limit`.
+    fn rate_limit_with_store<K, S>(self, key: K, policy: Policy, store: S) -> RateLimit<Self, K, S>
+    where
+        K: KeyExtractor,
+        S: Store,
+    {
+        RateLimit {
+            endpoint: self,
+            inner: Arc::new(rate_limit::Inner { key, policy, store }),
+        }
+    }
+
+    /// Create an endpoint which applies the specified CORS policy to `self`.
+    ///
+    /// The preflight requests routed to `self` (matched with the method in
+    /// `Access-Control-Request-Method`) are answered without evaluating `self`,
+    /// and the `Access-Control-*` headers are added to the responses of the actual
+    /// requests. See the documentation of `service::cors` for details.
+    fn cors(self, cors: Cors) -> WithCors<Self> {
+        WithCors {
+            endpoint: self,
+            cors,
+        }
+    }
+
+    /// Create an endpoint which compresses the response body created from the output of `self`.
+    ///
+    /// The encoding is negotiated from `Accept-Encoding` of the request when the output
+    /// is converted into the response. See the documentation of `service::compression`
+    /// for details.
+    ///
+    /// This method is available only if the feature `compression` is enabled.
+    #[cfg(feature = "compression")]
+    fn compress(self, compression: crate::service::compression::Compression) -> Compress<Self> {
+        Compress {
+            endpoint: self,
+            compression,
+        }
+    }
+
+    /// Create an endpoint which enters the specified span while the action
+    /// of `self` is being evaluated.
+    ///
+    /// The span is shared by all requests, so it is typically used to
+    /// attribute the events emitted within an endpoint to a particular route.
+    #[cfg(feature = "tracing")]
+    fn instrument(self, span: tracing::Span) -> Instrument<Self> {
+        Instrument {
+            endpoint: self,
+            span,
+        }
+    }
 }
 
 impl<E: IsEndpoint> EndpointExt for E {}
@@ -90,8 +204,7 @@ impl<E: IsEndpoint> EndpointExt for E {}
 /// An `HttpError` indicating that the endpoint could not determine the route.
 ///
 /// The value of this error is typically thrown from `Or` or `OrStrict`.
-#[derive(Debug, failure::Fail)]
-#[fail(display = "not matched")]
+#[derive(Debug)]
 pub struct NotMatched {
     /// The error value returned from the first endpoint.
     pub left: Error,
@@ -102,6 +215,14 @@ pub struct NotMatched {
     _priv: (),
 }
 
+impl fmt::Display for NotMatched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not matched")
+    }
+}
+
+impl std::error::Error for NotMatched {}
+
 impl HttpError for NotMatched {
     fn status_code(&self) -> http::StatusCode {
         http::StatusCode::NOT_FOUND
@@ -0,0 +1,68 @@
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+        service::compression::{Compressed, Compression},
+    },
+    futures::Poll,
+};
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct Compress<E> {
+    pub(super) endpoint: E,
+    pub(super) compression: Compression,
+}
+
+impl<E: IsEndpoint> IsEndpoint for Compress<E> {}
+
+impl<E, T, Bd> Endpoint<Bd> for Compress<E>
+where
+    E: Endpoint<Bd, Output = (T,)>,
+{
+    type Output = (Compressed<T>,);
+    type Action = CompressAction<E::Action>;
+
+    fn action(&self) -> Self::Action {
+        CompressAction {
+            action: self.endpoint.action(),
+            compression: self.compression.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CompressAction<A> {
+    action: A,
+    compression: Compression,
+}
+
+impl<A, T, Bd> EndpointAction<Bd> for CompressAction<A>
+where
+    A: EndpointAction<Bd, Output = (T,)>,
+{
+    type Output = (Compressed<T>,);
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        let compression = &self.compression;
+        self.action
+            .preflight(cx)
+            .map(|x| x.map(|(output,)| (Compressed::new(output, compression.clone()),)))
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        let compression = &self.compression;
+        self.action
+            .poll_action(cx)
+            .map(|x| x.map(|(output,)| (Compressed::new(output, compression.clone()),)))
+    }
+}
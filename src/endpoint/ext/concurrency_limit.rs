@@ -0,0 +1,89 @@
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+        service::load_shed::{Overloaded, Permit, Semaphore},
+    },
+    futures::Poll,
+    std::{sync::Arc, time::Duration},
+};
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit<E> {
+    pub(super) endpoint: E,
+    pub(super) semaphore: Arc<Semaphore>,
+    pub(super) retry_after: Duration,
+}
+
+impl<E> ConcurrencyLimit<E> {
+    /// Sets the value of `Retry-After` sent with the rejected responses.
+    ///
+    /// The default value is 1 second.
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        ConcurrencyLimit {
+            retry_after,
+            ..self
+        }
+    }
+}
+
+impl<E: IsEndpoint> IsEndpoint for ConcurrencyLimit<E> {}
+
+impl<E, Bd> Endpoint<Bd> for ConcurrencyLimit<E>
+where
+    E: Endpoint<Bd>,
+{
+    type Output = E::Output;
+    type Action = ConcurrencyLimitAction<E::Action>;
+
+    fn action(&self) -> Self::Action {
+        ConcurrencyLimitAction {
+            action: self.endpoint.action(),
+            semaphore: self.semaphore.clone(),
+            retry_after: self.retry_after,
+            permit: None,
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct ConcurrencyLimitAction<Act> {
+    action: Act,
+    semaphore: Arc<Semaphore>,
+    retry_after: Duration,
+    permit: Option<Permit>,
+}
+
+impl<Act, Bd> EndpointAction<Bd> for ConcurrencyLimitAction<Act>
+where
+    Act: EndpointAction<Bd>,
+{
+    type Output = Act::Output;
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        let preflight = self.action.preflight(cx)?;
+        if let Preflight::Incomplete = preflight {
+            // The rejection is reported from `poll_action`, since the errors
+            // in the preflight are regarded as "not matched" by `Or`.
+            self.permit = self.semaphore.try_acquire();
+        }
+        Ok(preflight)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        if self.permit.is_none() {
+            return Err(Overloaded::new(self.retry_after).into());
+        }
+        self.action.poll_action(cx)
+    }
+}
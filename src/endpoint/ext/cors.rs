@@ -0,0 +1,108 @@
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+        service::cors::{self, Cors},
+    },
+    either::Either,
+    futures::{Async, Poll},
+    http::Response,
+};
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct WithCors<E> {
+    pub(super) endpoint: E,
+    pub(super) cors: Cors,
+}
+
+impl<E: IsEndpoint> IsEndpoint for WithCors<E> {}
+
+impl<E, T, Bd> Endpoint<Bd> for WithCors<E>
+where
+    E: Endpoint<Bd, Output = (T,)>,
+{
+    type Output = (Either<Response<&'static [u8]>, T>,);
+    type Action = WithCorsAction<E::Action, T>;
+
+    fn action(&self) -> Self::Action {
+        WithCorsAction {
+            action: self.endpoint.action(),
+            cors: self.cors.clone(),
+            state: State::Init,
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct WithCorsAction<Act, T> {
+    action: Act,
+    cors: Cors,
+    state: State<T>,
+}
+
+enum State<T> {
+    Init,
+    Matched(Option<T>),
+    InFlight,
+}
+
+impl<Act, T, Bd> EndpointAction<Bd> for WithCorsAction<Act, T>
+where
+    Act: EndpointAction<Bd, Output = (T,)>,
+{
+    type Output = (Either<Response<&'static [u8]>, T>,);
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        if cors::is_preflight(cx.request()) {
+            // The subtree is matched against the method of the actual request,
+            // but the action itself is never evaluated.
+            let mut preflight_cx = cx.clone();
+            preflight_cx.set_method(cors::requested_method(cx.request()));
+            self.action.preflight(&mut preflight_cx)?;
+            preflight_cx.set_method(None);
+            *cx = preflight_cx;
+
+            let response = self.cors.preflight(cx.request()).map(|()| &b""[..]);
+            return Ok(Preflight::Completed((Either::Left(response),)));
+        }
+
+        // The headers are added in `poll_action`, after the endpoint has been
+        // chosen by the routing and the response headers can be modified.
+        let output = match self.action.preflight(cx)? {
+            Preflight::Completed((output,)) => Some(output),
+            Preflight::Incomplete => None,
+        };
+        self.state = State::Matched(output);
+        Ok(Preflight::Incomplete)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        loop {
+            self.state = match self.state {
+                State::Init => unreachable!("the preflight has not been called"),
+                State::Matched(ref mut output) => {
+                    let headers = self.cors.headers(cx.request());
+                    cx.context_mut().response_headers().extend(headers);
+                    if let Some(output) = output.take() {
+                        return Ok(Async::Ready((Either::Right(output),)));
+                    }
+                    State::InFlight
+                }
+                State::InFlight => {
+                    let (output,) = futures::try_ready!(self.action.poll_action(cx));
+                    return Ok(Async::Ready((Either::Right(output),)));
+                }
+            };
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+    },
+    futures::Poll,
+    tracing::Span,
+};
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct Instrument<E> {
+    pub(super) endpoint: E,
+    pub(super) span: Span,
+}
+
+impl<E: IsEndpoint> IsEndpoint for Instrument<E> {}
+
+impl<E, Bd> Endpoint<Bd> for Instrument<E>
+where
+    E: Endpoint<Bd>,
+{
+    type Output = E::Output;
+    type Action = InstrumentAction<E::Action>;
+
+    fn action(&self) -> Self::Action {
+        let _enter = self.span.enter();
+        InstrumentAction {
+            action: self.endpoint.action(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct InstrumentAction<A> {
+    action: A,
+    span: Span,
+}
+
+impl<A, Bd> EndpointAction<Bd> for InstrumentAction<A>
+where
+    A: EndpointAction<Bd>,
+{
+    type Output = A::Output;
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        let _enter = self.span.enter();
+        self.action.preflight(cx)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        let _enter = self.span.enter();
+        self.action.poll_action(cx)
+    }
+}
@@ -0,0 +1,126 @@
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        common::Tuple,
+        endpoint::{Endpoint, IsEndpoint},
+        endpoints::rate_limit::{KeyExtractor, MemoryStore, Policy, RateLimited, Store},
+        error::Error,
+    },
+    futures::{Async, Future, Poll},
+    std::sync::Arc,
+};
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct RateLimit<E, K, S = MemoryStore> {
+    pub(super) endpoint: E,
+    pub(super) inner: Arc<Inner<K, S>>,
+}
+
+#[derive(Debug)]
+pub(super) struct Inner<K, S> {
+    pub(super) key: K,
+    pub(super) policy: Policy,
+    pub(super) store: S,
+}
+
+impl<E: Clone, K, S> Clone for RateLimit<E, K, S> {
+    fn clone(&self) -> Self {
+        RateLimit {
+            endpoint: self.endpoint.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E: IsEndpoint, K, S> IsEndpoint for RateLimit<E, K, S> {}
+
+impl<E, K, S, Bd> Endpoint<Bd> for RateLimit<E, K, S>
+where
+    E: Endpoint<Bd>,
+    K: KeyExtractor,
+    S: Store,
+{
+    type Output = E::Output;
+    type Action = RateLimitAction<E::Action, K, S, E::Output>;
+
+    fn action(&self) -> Self::Action {
+        RateLimitAction {
+            action: self.endpoint.action(),
+            inner: self.inner.clone(),
+            state: State::Init,
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct RateLimitAction<Act, K, S: Store, T> {
+    action: Act,
+    inner: Arc<Inner<K, S>>,
+    state: State<S::Future, T>,
+}
+
+enum State<F, T> {
+    Init,
+    Matched(Option<T>),
+    Checking(F, Option<T>),
+    Allowed(Option<T>),
+}
+
+impl<Act, K, S, T, Bd> EndpointAction<Bd> for RateLimitAction<Act, K, S, T>
+where
+    Act: EndpointAction<Bd, Output = T>,
+    T: Tuple,
+    K: KeyExtractor,
+    S: Store,
+{
+    type Output = T;
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        // The quota is consumed in `poll_action`, after the endpoint has been
+        // chosen by the routing and the response headers can be modified.
+        let output = match self.action.preflight(cx)? {
+            Preflight::Completed(output) => Some(output),
+            Preflight::Incomplete => None,
+        };
+        self.state = State::Matched(output);
+        Ok(Preflight::Incomplete)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        loop {
+            self.state = match self.state {
+                State::Init => unreachable!("the preflight has not been called"),
+                State::Matched(ref mut output) => match self.inner.key.extract(cx.context()) {
+                    Some(key) => State::Checking(
+                        self.inner.store.check(&key, &self.inner.policy),
+                        output.take(),
+                    ),
+                    None => State::Allowed(output.take()),
+                },
+                State::Checking(ref mut future, ref mut output) => {
+                    let decision = futures::try_ready!(future.poll());
+                    decision.insert_headers(cx.context_mut().response_headers());
+                    if !decision.is_allowed() {
+                        return Err(RateLimited::new(decision).into());
+                    }
+                    State::Allowed(output.take())
+                }
+                State::Allowed(ref mut output) => {
+                    if let Some(output) = output.take() {
+                        return Ok(Async::Ready(output));
+                    }
+                    return self.action.poll_action(cx);
+                }
+            };
+        }
+    }
+}
@@ -59,6 +59,11 @@ pub trait ExtractPath {
     type Output: Tuple;
 
     fn extract(cx: &mut PreflightContext<'_>) -> Result<Self::Output, ExtractPathError>;
+
+    /// Returns the path literal from which this type is derived, used as the route template.
+    fn template() -> Option<&'static str> {
+        None
+    }
 }
 
 #[allow(missing_docs)]
@@ -132,7 +137,11 @@ mod path {
 
         #[inline]
         fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
-            <T as ExtractPath>::extract(cx).map_err(Into::into)
+            let output = <T as ExtractPath>::extract(cx).map_err(Into::<Error>::into)?;
+            if let Some(template) = T::template() {
+                cx.push_route(template);
+            }
+            Ok(output)
         }
     }
 }
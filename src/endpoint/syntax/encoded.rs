@@ -2,11 +2,11 @@
 
 use {
     crate::error::{Error, HttpError},
-    failure::Fail,
     http::StatusCode,
     percent_encoding::percent_decode,
     std::{
         borrow::Cow,
+        error::Error as StdError,
         fmt, net,
         path::PathBuf,
         str::{self, FromStr, Utf8Error},
@@ -150,13 +150,23 @@ impl FromEncodedStr for PathBuf {
 }
 
 #[allow(missing_docs)]
-#[derive(Debug, Fail)]
-#[fail(display = "failed to decode a percent encoded string to UTF-8")]
+#[derive(Debug)]
 pub struct DecodeEncodedStrError {
-    #[fail(source)]
     cause: Utf8Error,
 }
 
+impl fmt::Display for DecodeEncodedStrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to decode a percent encoded string to UTF-8")
+    }
+}
+
+impl StdError for DecodeEncodedStrError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.cause)
+    }
+}
+
 impl HttpError for DecodeEncodedStrError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
@@ -164,19 +174,35 @@ impl HttpError for DecodeEncodedStrError {
 }
 
 #[allow(missing_docs)]
-#[derive(Debug, Fail)]
-#[fail(display = "{}", cause)]
+#[derive(Debug)]
 pub struct ParseEncodedStrError<E>
 where
-    E: Fail + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
 {
-    #[fail(cause)]
     cause: E,
 }
 
+impl<E> fmt::Display for ParseEncodedStrError<E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.cause, f)
+    }
+}
+
+impl<E> StdError for ParseEncodedStrError<E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.cause)
+    }
+}
+
 impl<E> HttpError for ParseEncodedStrError<E>
 where
-    E: Fail + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
 {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
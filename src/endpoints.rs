@@ -2,5 +2,9 @@
 
 pub mod body;
 pub mod fs;
+pub mod health;
 pub mod header;
+pub mod metrics;
 pub mod query;
+pub mod rate_limit;
+pub mod request_id;
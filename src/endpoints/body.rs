@@ -1,5 +1,7 @@
 //! Endpoints for parsing the message body.
 
+pub mod multipart;
+
 use {
     crate::{
         action::{
@@ -9,16 +11,64 @@ use {
             PreflightContext,
         },
         endpoint::{Endpoint, IsEndpoint},
-        error::{self, Error},
+        error::{self, Error, HttpError},
+        service::Context,
     },
+    encoding_rs::{Encoding, UTF_8},
     futures::Poll,
-    http::Request,
+    http::{header, Request, StatusCode},
     izanami_util::buf_stream::BufStream,
     mime::Mime,
     serde::de::DeserializeOwned,
-    std::{cell::UnsafeCell, marker::PhantomData},
+    std::{cell::UnsafeCell, fmt, marker::PhantomData, sync::Arc},
 };
 
+#[cfg(not(feature = "compression"))]
+use self::decompression::Decoded;
+#[cfg(feature = "compression")]
+use crate::service::decompression::{self, Decoded};
+
+/// The replacement of `service::decompression` without the feature `compression`,
+/// which passes through the request bodies as they are.
+#[cfg(not(feature = "compression"))]
+mod decompression {
+    use {
+        crate::{
+            error::{self, Error},
+            service::Context,
+        },
+        futures::Poll,
+        http::StatusCode,
+        izanami_util::buf_stream::{BufStream, SizeHint},
+    };
+
+    #[derive(Debug)]
+    pub(crate) struct Decoded<Bd>(Bd);
+
+    pub(crate) fn decode<Bd>(body: Bd, _: &Context) -> Result<Decoded<Bd>, Error> {
+        Ok(Decoded(body))
+    }
+
+    impl<Bd> BufStream for Decoded<Bd>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        type Item = Bd::Item;
+        type Error = Error;
+
+        fn poll_buf(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+            self.0
+                .poll_buf()
+                .map_err(|e| error::from_std(e, StatusCode::INTERNAL_SERVER_ERROR))
+        }
+
+        fn size_hint(&self) -> SizeHint {
+            self.0.size_hint()
+        }
+    }
+}
+
 fn content_type<T>(request: &Request<T>) -> crate::error::Result<Option<Mime>> {
     if let Some(h) = request.headers().get(http::header::CONTENT_TYPE) {
         let mime = h
@@ -75,18 +125,45 @@ mod raw {
     }
 }
 
+/// Rejects the request early if its length is known to exceed the limit.
+fn check_content_length(limit: Option<u64>, cx: &PreflightContext<'_>) -> Result<(), Error> {
+    if let Some(limit) = limit.or_else(|| cx.body_limit()) {
+        let content_length = cx
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<u64>().ok());
+        if content_length.map_or(false, |len| len > limit) {
+            return Err(PayloadTooLarge::new(limit).into());
+        }
+    }
+    Ok(())
+}
+
 /// Creates an endpoint which receives all of request body.
 ///
 /// If the instance of `BufStream` has already been stolen by another endpoint, it will
 /// return an error.
+///
+/// The length of the body is limited by `App::body_limit`, or by `ReceiveAll::limit`
+/// if specified. The requests exceeding the limit are rejected with `PayloadTooLarge`.
 #[inline]
 pub fn receive_all() -> ReceiveAll {
-    ReceiveAll(())
+    ReceiveAll { limit: None }
 }
 
 #[allow(missing_docs)]
 #[derive(Debug)]
-pub struct ReceiveAll(());
+pub struct ReceiveAll {
+    limit: Option<u64>,
+}
+
+impl ReceiveAll {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        ReceiveAll { limit: Some(limit) }
+    }
+}
 
 mod receive_all {
     use super::*;
@@ -103,19 +180,20 @@ mod receive_all {
         type Action = ReceiveAllAction<Bd>;
 
         fn action(&self) -> Self::Action {
-            new_action()
+            new_action(self.limit)
         }
     }
 
     #[allow(missing_debug_implementations)]
     pub struct ReceiveAllAction<Bd> {
+        limit: Option<u64>,
         state: State<Bd>,
     }
 
-    #[allow(missing_debug_implementations)]
+    #[allow(missing_debug_implementations, clippy::large_enum_variant)]
     enum State<Bd> {
         Start,
-        Receiving(Bd, Vec<u8>),
+        Receiving(Decoded<Bd>, Vec<u8>),
     }
 
     impl<Bd> EndpointAction<Bd> for ReceiveAllAction<Bd>
@@ -125,18 +203,30 @@ mod receive_all {
     {
         type Output = (Vec<u8>,);
 
+        fn preflight(
+            &mut self,
+            cx: &mut PreflightContext<'_>,
+        ) -> Result<Preflight<Self::Output>, Error> {
+            check_content_length(self.limit, cx)?;
+            Ok(Preflight::Incomplete)
+        }
+
         fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
             loop {
                 self.state = match self.state {
                     State::Start => {
+                        self.limit = self.limit.or_else(|| cx.body_limit());
                         let payload = cx.take_body()?;
+                        let payload = decompression::decode(payload, cx.context())?;
                         State::Receiving(payload, Vec::new())
                     }
                     State::Receiving(ref mut body, ref mut buf) => {
-                        while let Some(data) = futures::try_ready!(body
-                            .poll_buf()
-                            .map_err(|e| failure::Error::from_boxed_compat(e.into())))
-                        {
+                        while let Some(data) = futures::try_ready!(body.poll_buf()) {
+                            if let Some(limit) = self.limit {
+                                if (buf.len() + data.remaining()) as u64 > limit {
+                                    return Err(PayloadTooLarge::new(limit).into());
+                                }
+                            }
                             buf.extend_from_slice(data.bytes());
                         }
                         let buf = std::mem::replace(buf, Vec::new());
@@ -147,23 +237,175 @@ mod receive_all {
         }
     }
 
-    pub(super) fn new_action<Bd>() -> ReceiveAllAction<Bd>
+    pub(super) fn new_action<Bd>(limit: Option<u64>) -> ReceiveAllAction<Bd>
     where
         Bd: BufStream,
     {
         ReceiveAllAction {
+            limit,
             state: State::Start,
         }
     }
 }
 
+// ==== Stream ====
+
+/// Creates an endpoint which takes the request body as a `Stream` of chunks.
+///
+/// Unlike `receive_all()`, the chunks are not buffered by the endpoint, so the handler
+/// can process a large body incrementally, e.g. writing it into a file. The next chunk
+/// is not read from the connection until the stream is polled again.
+///
+/// The body is decompressed if enabled by `App::decompression`, and its length is
+/// limited in the same way as `receive_all()`.
+///
+/// If the instance of request body has already been stolen by another endpoint,
+/// it will return an error.
+#[inline]
+pub fn stream() -> ReceiveStream {
+    ReceiveStream { limit: None }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct ReceiveStream {
+    limit: Option<u64>,
+}
+
+impl ReceiveStream {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        ReceiveStream { limit: Some(limit) }
+    }
+}
+
+/// A `Stream` of the chunks of the request body, returned from `body::stream()`.
+#[derive(Debug)]
+pub struct BodyStream<Bd> {
+    body: Decoded<Bd>,
+    limit: Option<u64>,
+    received: u64,
+}
+
+mod stream {
+    use super::*;
+    use bytes::{Buf, Bytes};
+    use futures::{Async, Stream};
+
+    impl IsEndpoint for ReceiveStream {}
+
+    impl<Bd> Endpoint<Bd> for ReceiveStream
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        type Output = (BodyStream<Bd>,);
+        type Action = ReceiveStreamAction<Bd>;
+
+        fn action(&self) -> Self::Action {
+            ReceiveStreamAction {
+                limit: self.limit,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations, clippy::type_complexity)]
+    pub struct ReceiveStreamAction<Bd> {
+        limit: Option<u64>,
+        _marker: PhantomData<(UnsafeCell<()>, fn(Bd))>,
+    }
+
+    impl<Bd> EndpointAction<Bd> for ReceiveStreamAction<Bd>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        type Output = (BodyStream<Bd>,);
+
+        fn preflight(
+            &mut self,
+            cx: &mut PreflightContext<'_>,
+        ) -> Result<Preflight<Self::Output>, Error> {
+            check_content_length(self.limit, cx)?;
+            Ok(Preflight::Incomplete)
+        }
+
+        fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+            let limit = self.limit.or_else(|| cx.body_limit());
+            let body = cx.take_body()?;
+            let body = decompression::decode(body, cx.context())?;
+            Ok(Async::Ready((BodyStream {
+                body,
+                limit,
+                received: 0,
+            },)))
+        }
+    }
+
+    impl<Bd> Stream for BodyStream<Bd>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        type Item = Bytes;
+        type Error = Error;
+
+        fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+            let chunk = match futures::try_ready!(self.body.poll_buf()) {
+                Some(chunk) => chunk,
+                None => return Ok(Async::Ready(None)),
+            };
+            self.received += chunk.remaining() as u64;
+            if let Some(limit) = self.limit {
+                if self.received > limit {
+                    return Err(PayloadTooLarge::new(limit).into());
+                }
+            }
+            Ok(Async::Ready(Some(chunk.collect())))
+        }
+    }
+}
+
+// ==== Multipart ====
+
+/// Creates an endpoint which receives a `multipart/form-data` body as a stream of parts.
+///
+/// The request is rejected with `400 Bad Request` if its `Content-Type` is not
+/// `multipart/form-data` or lacks the boundary. The length of the body is limited
+/// in the same way as `receive_all()`.
+///
+/// See the documentation of [`multipart`](./multipart/index.html) for details.
+#[inline]
+pub fn multipart() -> multipart::ReceiveMultipart {
+    multipart::ReceiveMultipart::new()
+}
+
+/// Creates an endpoint which collects a `multipart/form-data` body into a `Form<T>`.
+///
+/// The text fields are deserialized into `T`, and the uploaded files are spooled into
+/// the temporary files. The size of each file is limited by `MultipartForm::file_limit`,
+/// and the total size is limited by `MultipartForm::limit` or `App::body_limit`.
+#[inline]
+pub fn multipart_form<T>() -> multipart::MultipartForm<T>
+where
+    T: DeserializeOwned,
+{
+    multipart::MultipartForm::new()
+}
+
 // ==== Text ====
 
 /// Create an endpoint which parses a request body into `String`.
+///
+/// The body is decoded according to the `charset` parameter of `Content-Type`,
+/// e.g. `Shift_JIS`, `ISO-8859-1`, `windows-1252` or `UTF-16`. The body is assumed
+/// to be encoded in UTF-8 if the parameter is missing.
 #[inline]
 pub fn text() -> Text {
     Text {
         receive_all: receive_all(),
+        strict: false,
     }
 }
 
@@ -171,6 +413,25 @@ pub fn text() -> Text {
 #[derive(Debug)]
 pub struct Text {
     receive_all: ReceiveAll,
+    strict: bool,
+}
+
+impl Text {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        Text {
+            receive_all: self.receive_all.limit(limit),
+            ..self
+        }
+    }
+
+    /// Rejects the requests whose `charset` is not UTF-8, instead of decoding them.
+    pub fn strict_charset(self) -> Self {
+        Text {
+            strict: true,
+            ..self
+        }
+    }
 }
 
 mod text {
@@ -188,7 +449,8 @@ mod text {
 
         fn action(&self) -> Self::Action {
             TextAction {
-                receive_all: super::receive_all::new_action(),
+                receive_all: super::receive_all::new_action(self.receive_all.limit),
+                strict: self.strict,
             }
         }
     }
@@ -200,6 +462,7 @@ mod text {
         Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
     {
         receive_all: super::receive_all::ReceiveAllAction<Bd>,
+        strict: bool,
     }
 
     impl<Bd> EndpointAction<Bd> for TextAction<Bd>
@@ -213,24 +476,17 @@ mod text {
             &mut self,
             cx: &mut PreflightContext<'_>,
         ) -> Result<Preflight<Self::Output>, Error> {
-            if let Some(param) = content_type(&*cx)?
-                .as_ref()
-                .and_then(|m| m.get_param("charset"))
-            {
-                if param != "utf-8" {
-                    return Err(error::bad_request("Only the UTF-8 charset is supported."));
-                }
-            }
+            charset::encoding(cx, self.strict)?;
 
-            Ok(Preflight::Incomplete)
+            self.receive_all
+                .preflight(cx)
+                .map(|_| Preflight::Incomplete)
         }
 
         fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
             let (data,) = futures::try_ready!(self.receive_all.poll_action(cx));
-            String::from_utf8(data.to_vec())
-                .map(|x| (x,).into())
-                .map_err(error::bad_request)
-                .map_err(Into::into)
+            let encoding = charset::encoding(cx, self.strict)?;
+            charset::decode(data, encoding).map(|x| (x,).into())
         }
     }
 }
@@ -242,22 +498,34 @@ where
     T: DeserializeOwned,
 {
     Json {
+        limit: None,
         _marker: PhantomData,
     }
 }
 
 #[allow(missing_docs)]
 pub struct Json<T> {
+    limit: Option<u64>,
     _marker: PhantomData<fn() -> T>,
 }
 
+impl<T> Json<T> {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        Json {
+            limit: Some(limit),
+            ..self
+        }
+    }
+}
+
 mod json {
     use super::*;
     use std::fmt;
 
     impl<T> fmt::Debug for Json<T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            f.debug_struct("Json").finish()
+            f.debug_struct("Json").field("limit", &self.limit).finish()
         }
     }
 
@@ -274,7 +542,7 @@ mod json {
 
         fn action(&self) -> Self::Action {
             JsonAction {
-                receive_all: super::receive_all::new_action(),
+                receive_all: super::receive_all::new_action(self.limit),
                 _marker: PhantomData,
             }
         }
@@ -310,7 +578,9 @@ mod json {
                 ));
             }
 
-            Ok(Preflight::Incomplete)
+            self.receive_all
+                .preflight(cx)
+                .map(|_| Preflight::Incomplete)
         }
 
         fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
@@ -325,28 +595,56 @@ mod json {
 // ==== UrlEncoded ====
 
 /// Create an endpoint which parses an urlencoded data.
+///
+/// The names and values are decoded according to the `charset` parameter of
+/// `Content-Type`, as in `text()`.
 #[inline]
 pub fn urlencoded<T>() -> Urlencoded<T>
 where
     T: DeserializeOwned,
 {
     Urlencoded {
+        limit: None,
+        strict: false,
         _marker: PhantomData,
     }
 }
 
 #[allow(missing_docs)]
 pub struct Urlencoded<T> {
+    limit: Option<u64>,
+    strict: bool,
     _marker: PhantomData<fn() -> T>,
 }
 
+impl<T> Urlencoded<T> {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        Urlencoded {
+            limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Rejects the requests whose `charset` is not UTF-8, instead of decoding them.
+    pub fn strict_charset(self) -> Self {
+        Urlencoded {
+            strict: true,
+            ..self
+        }
+    }
+}
+
 mod urlencoded {
     use super::*;
-    use {failure::SyncFailure, std::fmt};
+    use std::fmt;
 
     impl<T> fmt::Debug for Urlencoded<T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            f.debug_struct("Json").finish()
+            f.debug_struct("Urlencoded")
+                .field("limit", &self.limit)
+                .field("strict", &self.strict)
+                .finish()
         }
     }
 
@@ -363,7 +661,8 @@ mod urlencoded {
 
         fn action(&self) -> Self::Action {
             UrlencodedAction {
-                receive_all: super::receive_all::new_action(),
+                receive_all: super::receive_all::new_action(self.limit),
+                strict: self.strict,
                 _marker: PhantomData,
             }
         }
@@ -376,6 +675,7 @@ mod urlencoded {
         Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
     {
         receive_all: super::receive_all::ReceiveAllAction<Bd>,
+        strict: bool,
         _marker: PhantomData<fn() -> T>,
     }
 
@@ -393,21 +693,597 @@ mod urlencoded {
         ) -> Result<Preflight<Self::Output>, Error> {
             let mime = content_type(&*cx)? //
                 .ok_or_else(|| error::bad_request("missing content type"))?;
-            if mime != mime::APPLICATION_WWW_FORM_URLENCODED {
+            if mime.type_() != mime::APPLICATION || mime.subtype() != mime::WWW_FORM_URLENCODED {
                 return Err(error::bad_request(
                     "The value of `Content-type` must be `application-x-www-form-urlencoded`.",
                 ));
             }
+            charset::encoding(cx, self.strict)?;
 
-            Ok(Preflight::Incomplete)
+            self.receive_all
+                .preflight(cx)
+                .map(|_| Preflight::Incomplete)
         }
 
         fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
             let (data,) = futures::try_ready!(self.receive_all.poll_action(cx));
-            let s = std::str::from_utf8(&*data).map_err(error::bad_request)?;
-            serde_qs::from_str(s)
+            let encoding = charset::encoding(cx, self.strict)?;
+            let s = charset::decode_urlencoded(&data, encoding)?;
+            serde_qs::from_str(&s)
                 .map(|x| (x,).into())
-                .map_err(|err| error::bad_request(SyncFailure::new(err)))
+                .map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST))
         }
     }
 }
+
+// ==== Parse ====
+
+/// Create an endpoint which parses a request body in the format specified by `Content-Type`.
+///
+/// The formats JSON (`application/json`, including the types with the `+json` suffix
+/// such as `application/ld+json`) and `application/x-www-form-urlencoded` are supported
+/// by default, as well as MessagePack, CBOR and XML if the features `msgpack`, `cbor`
+/// and `xml` are enabled. Other formats can be added by `Parse::format`. The requests
+/// with other content types are rejected with `UnsupportedMediaType`.
+#[inline]
+pub fn parse<T>() -> Parse<T>
+where
+    T: DeserializeOwned,
+{
+    let parse = Parse::new().json().urlencoded();
+    #[cfg(feature = "msgpack")]
+    let parse = parse.msgpack();
+    #[cfg(feature = "cbor")]
+    let parse = parse.cbor();
+    #[cfg(feature = "xml")]
+    let parse = parse.xml();
+    parse
+}
+
+/// Create an endpoint which parses a request body into a MessagePack data.
+///
+/// The value of `Content-Type` must be `application/msgpack` or `application/x-msgpack`,
+/// otherwise the request is rejected with `UnsupportedMediaType`.
+///
+/// This function is available only if the feature `msgpack` is enabled.
+#[cfg(feature = "msgpack")]
+#[inline]
+pub fn msgpack<T>() -> Parse<T>
+where
+    T: DeserializeOwned,
+{
+    Parse::new().msgpack()
+}
+
+/// Create an endpoint which parses a request body into a CBOR data.
+///
+/// The value of `Content-Type` must be `application/cbor`, otherwise the request
+/// is rejected with `UnsupportedMediaType`.
+///
+/// This function is available only if the feature `cbor` is enabled.
+#[cfg(feature = "cbor")]
+#[inline]
+pub fn cbor<T>() -> Parse<T>
+where
+    T: DeserializeOwned,
+{
+    Parse::new().cbor()
+}
+
+/// Create an endpoint which parses a request body into a XML data.
+///
+/// The value of `Content-Type` must be `application/xml`, `text/xml` or a type with
+/// the `+xml` suffix, otherwise the request is rejected with `UnsupportedMediaType`.
+/// The body is decoded according to the byte order mark, the `charset` parameter of
+/// `Content-Type` or the `encoding` declaration in the prolog, in that order, and is
+/// assumed to be encoded in UTF-8 if none of them is present.
+///
+/// This function is available only if the feature `xml` is enabled.
+#[cfg(feature = "xml")]
+#[inline]
+pub fn xml<T>() -> Parse<T>
+where
+    T: DeserializeOwned,
+{
+    Parse::new().xml()
+}
+
+#[allow(missing_docs)]
+pub struct Parse<T> {
+    limit: Option<u64>,
+    strict: bool,
+    formats: Arc<Vec<Format<T>>>,
+}
+
+struct Format<T> {
+    mime: Mime,
+    // Whether the format decodes the body according to `charset`. The body is passed
+    // to the other formats as is.
+    decodes_charset: bool,
+    parse: ParseFn<T>,
+}
+
+type ParseFn<T> =
+    Box<dyn Fn(&[u8], Option<&'static Encoding>) -> Result<T, Error> + Send + Sync + 'static>;
+
+impl<T> Format<T> {
+    /// Returns whether the content type is acceptable for this format.
+    ///
+    /// The content type matches if its type and subtype are the same as the format,
+    /// or its structured syntax suffix is the subtype of the format,
+    /// e.g. `application/vnd.api+json` for `application/json`.
+    fn matches(&self, mime: &Mime) -> bool {
+        mime.type_() == self.mime.type_()
+            && (mime.subtype() == self.mime.subtype() || mime.suffix() == Some(self.mime.subtype()))
+    }
+}
+
+impl<T: DeserializeOwned> Parse<T> {
+    fn new() -> Self {
+        Parse {
+            limit: None,
+            strict: false,
+            formats: Arc::new(vec![]),
+        }
+    }
+
+    fn json(self) -> Self {
+        self.format(mime::APPLICATION_JSON, |data: &[u8]| {
+            serde_json::from_slice(data)
+        })
+    }
+
+    fn urlencoded(self) -> Self {
+        self.push_format(
+            mime::APPLICATION_WWW_FORM_URLENCODED,
+            true,
+            |data, encoding| {
+                let s = charset::decode_urlencoded(data, encoding.unwrap_or(UTF_8))?;
+                serde_qs::from_str(&s).map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST))
+            },
+        )
+    }
+
+    #[cfg(feature = "msgpack")]
+    fn msgpack(self) -> Self {
+        let parse = |data: &[u8]| rmp_serde::from_slice(data);
+        self.format(static_mime("application/msgpack"), parse)
+            .format(static_mime("application/x-msgpack"), parse)
+    }
+
+    #[cfg(feature = "cbor")]
+    fn cbor(self) -> Self {
+        self.format(static_mime("application/cbor"), |data: &[u8]| {
+            serde_cbor::from_slice(data)
+        })
+    }
+
+    #[cfg(feature = "xml")]
+    fn xml(self) -> Self {
+        let parse = |data: &[u8], encoding| {
+            let s = charset::decode_xml(data, encoding)?;
+            serde_xml_rs::from_str(&s).map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST))
+        };
+        self.push_format(static_mime("application/xml"), true, parse)
+            .push_format(mime::TEXT_XML, true, parse)
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "xml"))]
+fn static_mime(s: &'static str) -> Mime {
+    s.parse().expect("should be a valid MIME type")
+}
+
+impl<T> Parse<T> {
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        Parse {
+            limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Rejects the urlencoded and XML requests whose `charset` is not UTF-8, instead of decoding them.
+    pub fn strict_charset(self) -> Self {
+        Parse {
+            strict: true,
+            ..self
+        }
+    }
+
+    /// Registers a format with the content type and the function deserializing the body.
+    ///
+    /// The parameters of `mime` are ignored when matching. If the function returns an
+    /// error, the request is rejected with `400 Bad Request`.
+    pub fn format<F, E>(self, mime: Mime, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<T, E> + Send + Sync + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        self.push_format(mime, false, move |data, _| {
+            f(data).map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST))
+        })
+    }
+
+    fn push_format<F>(mut self, mime: Mime, decodes_charset: bool, parse: F) -> Self
+    where
+        F: Fn(&[u8], Option<&'static Encoding>) -> Result<T, Error> + Send + Sync + 'static,
+    {
+        Arc::get_mut(&mut self.formats)
+            .expect("the formats have already been shared")
+            .push(Format {
+                mime,
+                decodes_charset,
+                parse: Box::new(parse),
+            });
+        self
+    }
+
+    fn select(&self, cx: &Context) -> Result<(usize, Option<&'static Encoding>), Error> {
+        let unsupported = || -> Error {
+            UnsupportedMediaType::new(
+                self.formats
+                    .iter()
+                    .map(|format| format.mime.clone())
+                    .collect(),
+            )
+            .into()
+        };
+        let index = content_type(cx)?
+            .and_then(|mime| self.formats.iter().position(|format| format.matches(&mime)))
+            .ok_or_else(unsupported)?;
+        if self.formats[index].decodes_charset {
+            Ok((index, charset::specified(cx, self.strict)?))
+        } else {
+            Ok((index, None))
+        }
+    }
+}
+
+mod parse {
+    use super::*;
+    use std::fmt;
+
+    impl<T> fmt::Debug for Parse<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Parse")
+                .field("limit", &self.limit)
+                .field("strict", &self.strict)
+                .field(
+                    "formats",
+                    &self
+                        .formats
+                        .iter()
+                        .map(|format| &format.mime)
+                        .collect::<Vec<_>>(),
+                )
+                .finish()
+        }
+    }
+
+    impl<T: DeserializeOwned> IsEndpoint for Parse<T> {}
+
+    impl<T, Bd> Endpoint<Bd> for Parse<T>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+        T: DeserializeOwned,
+    {
+        type Output = (T,);
+        type Action = ParseAction<Bd, T>;
+
+        fn action(&self) -> Self::Action {
+            ParseAction {
+                receive_all: super::receive_all::new_action(self.limit),
+                parse: Parse {
+                    limit: self.limit,
+                    strict: self.strict,
+                    formats: self.formats.clone(),
+                },
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct ParseAction<Bd, T>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        receive_all: super::receive_all::ReceiveAllAction<Bd>,
+        parse: Parse<T>,
+    }
+
+    impl<Bd, T> EndpointAction<Bd> for ParseAction<Bd, T>
+    where
+        Bd: BufStream,
+        Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+        T: DeserializeOwned,
+    {
+        type Output = (T,);
+
+        fn preflight(
+            &mut self,
+            cx: &mut PreflightContext<'_>,
+        ) -> Result<Preflight<Self::Output>, Error> {
+            self.parse.select(cx)?;
+            self.receive_all
+                .preflight(cx)
+                .map(|_| Preflight::Incomplete)
+        }
+
+        fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+            let (data,) = futures::try_ready!(self.receive_all.poll_action(cx));
+            let (index, encoding) = self.parse.select(cx)?;
+            (self.parse.formats[index].parse)(&*data, encoding).map(|x| (x,).into())
+        }
+    }
+}
+
+// ==== charset ====
+
+mod charset {
+    use super::*;
+    use percent_encoding::{percent_decode, utf8_percent_encode, SIMPLE_ENCODE_SET};
+    use std::borrow::Cow;
+
+    percent_encoding::define_encode_set! {
+        /// The encode set for re-encoding the names and values of urlencoded data.
+        pub FORM_ENCODE_SET = [SIMPLE_ENCODE_SET] | {' ', '"', '#', '%', '&', '+', '<', '=', '>'}
+    }
+
+    /// Returns the encoding specified by the `charset` parameter of `Content-Type`,
+    /// or UTF-8 if the parameter is missing.
+    ///
+    /// If `strict` is true, only UTF-8 is accepted.
+    pub(super) fn encoding(cx: &Context, strict: bool) -> Result<&'static Encoding, Error> {
+        specified(cx, strict).map(|encoding| encoding.unwrap_or(UTF_8))
+    }
+
+    /// Returns the encoding specified by the `charset` parameter of `Content-Type`, if any.
+    pub(super) fn specified(
+        cx: &Context,
+        strict: bool,
+    ) -> Result<Option<&'static Encoding>, Error> {
+        let mime = content_type(cx)?;
+        let label = match mime.as_ref().and_then(|mime| mime.get_param(mime::CHARSET)) {
+            Some(charset) => charset.as_str(),
+            None => return Ok(None),
+        };
+        match Encoding::for_label(label.as_bytes()) {
+            Some(encoding) if encoding == UTF_8 => Ok(Some(UTF_8)),
+            Some(..) if strict => Err(error::bad_request("Only the UTF-8 charset is supported.")),
+            Some(encoding) => Ok(Some(encoding)),
+            None => Err(error::bad_request(format!(
+                "unsupported charset: {}",
+                label
+            ))),
+        }
+    }
+
+    /// Decodes the text into `String`.
+    ///
+    /// The byte order mark is removed. For the encodings other than UTF-8, it takes
+    /// precedence over the specified encoding, e.g. for distinguishing UTF-16BE from
+    /// UTF-16LE.
+    pub(super) fn decode(mut data: Vec<u8>, encoding: &'static Encoding) -> Result<String, Error> {
+        if encoding == UTF_8 {
+            if data.starts_with(b"\xEF\xBB\xBF") {
+                data.drain(..3);
+            }
+            return String::from_utf8(data).map_err(error::bad_request);
+        }
+        let (encoding, data) = match Encoding::for_bom(&data) {
+            Some((encoding, len)) => (encoding, &data[len..]),
+            None => (encoding, &data[..]),
+        };
+        decode_bytes(data, encoding).map(Cow::into_owned)
+    }
+
+    /// Decodes the urlencoded data and re-encodes it as UTF-8.
+    ///
+    /// The encodings which are not ASCII-compatible (e.g. UTF-16) are rejected,
+    /// since the delimiters `&` and `=` cannot be found in the raw bytes.
+    pub(super) fn decode_urlencoded<'a>(
+        data: &'a [u8],
+        encoding: &'static Encoding,
+    ) -> Result<Cow<'a, str>, Error> {
+        if encoding == UTF_8 {
+            return std::str::from_utf8(data)
+                .map(Cow::Borrowed)
+                .map_err(error::bad_request);
+        }
+        if !encoding.is_ascii_compatible() {
+            return Err(error::err_msg(
+                format!(
+                    "unsupported charset for urlencoded data: {}",
+                    encoding.name()
+                ),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ));
+        }
+
+        let mut encoded = String::new();
+        for pair in data.split(|&b| b == b'&').filter(|pair| !pair.is_empty()) {
+            let mut iter = pair.splitn(2, |&b| b == b'=');
+            let name = decode_component(iter.next().unwrap_or_default(), encoding)?;
+            let value = decode_component(iter.next().unwrap_or_default(), encoding)?;
+            if !encoded.is_empty() {
+                encoded.push('&');
+            }
+            encoded.extend(utf8_percent_encode(&name, FORM_ENCODE_SET));
+            encoded.push('=');
+            encoded.extend(utf8_percent_encode(&value, FORM_ENCODE_SET));
+        }
+        Ok(Cow::Owned(encoded))
+    }
+
+    /// Decodes the XML document into `String`.
+    ///
+    /// The encoding is determined by the byte order mark, the specified `charset` and
+    /// the `encoding` declaration in the prolog, in that order (RFC 7303, section 3.2).
+    #[cfg(feature = "xml")]
+    pub(super) fn decode_xml<'a>(
+        data: &'a [u8],
+        charset: Option<&'static Encoding>,
+    ) -> Result<Cow<'a, str>, Error> {
+        let (encoding, data) = match Encoding::for_bom(data) {
+            Some((encoding, len)) => (encoding, &data[len..]),
+            None => match charset {
+                Some(encoding) => (encoding, data),
+                None => match xml_encoding_decl(data) {
+                    Some(range) => {
+                        let label = &data[range];
+                        let encoding = Encoding::for_label(label).ok_or_else(|| {
+                            error::bad_request(format!(
+                                "unsupported encoding: {}",
+                                String::from_utf8_lossy(label)
+                            ))
+                        })?;
+                        (encoding, data)
+                    }
+                    None => (UTF_8, data),
+                },
+            },
+        };
+        let mut decoded = decode_bytes(data, encoding)?;
+
+        // The parser honors the declaration, so it must match the decoded text.
+        if let Some(range) = xml_encoding_decl(decoded.as_bytes()) {
+            if !decoded[range.clone()].eq_ignore_ascii_case("UTF-8") {
+                decoded.to_mut().replace_range(range, "UTF-8");
+            }
+        }
+        Ok(decoded)
+    }
+
+    /// Returns the range of the value of `encoding` in the XML declaration.
+    #[cfg(feature = "xml")]
+    fn xml_encoding_decl(data: &[u8]) -> Option<std::ops::Range<usize>> {
+        fn find(data: &[u8], pat: &[u8]) -> Option<usize> {
+            data.windows(pat.len()).position(|w| w == pat)
+        }
+        fn skip_spaces(data: &[u8], pos: usize) -> usize {
+            pos + data[pos..]
+                .iter()
+                .take_while(|b| b" \t\r\n".contains(b))
+                .count()
+        }
+
+        if !data.starts_with(b"<?xml") {
+            return None;
+        }
+        let decl = &data[..find(data, b"?>")?];
+        let pos = skip_spaces(decl, find(decl, b"encoding")? + b"encoding".len());
+        if decl.get(pos) != Some(&b'=') {
+            return None;
+        }
+        let pos = skip_spaces(decl, pos + 1);
+        let quote = match decl.get(pos) {
+            Some(&quote) if quote == b'"' || quote == b'\'' => quote,
+            _ => return None,
+        };
+        let start = pos + 1;
+        let len = decl[start..].iter().position(|&b| b == quote)?;
+        Some(start..start + len)
+    }
+
+    fn decode_component(s: &[u8], encoding: &'static Encoding) -> Result<String, Error> {
+        let s: Vec<u8> = s
+            .iter()
+            .map(|&b| if b == b'+' { b' ' } else { b })
+            .collect();
+        let s: Vec<u8> = percent_decode(&s).collect();
+        decode_bytes(&s, encoding).map(Cow::into_owned)
+    }
+
+    fn decode_bytes<'a>(
+        data: &'a [u8],
+        encoding: &'static Encoding,
+    ) -> Result<Cow<'a, str>, Error> {
+        encoding
+            .decode_without_bom_handling_and_without_replacement(data)
+            .ok_or_else(|| error::bad_request(format!("malformed {} data", encoding.name())))
+    }
+}
+
+// ==== PayloadTooLarge ====
+
+/// An `HttpError` indicating that the request body exceeds the limit.
+///
+/// The response is `413 Payload Too Large`.
+#[derive(Debug)]
+pub struct PayloadTooLarge {
+    limit: u64,
+}
+
+impl PayloadTooLarge {
+    pub(crate) fn new(limit: u64) -> Self {
+        PayloadTooLarge { limit }
+    }
+
+    /// Returns the maximum length of the request body.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+}
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the request body exceeds the limit of {} bytes",
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+impl HttpError for PayloadTooLarge {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::PAYLOAD_TOO_LARGE
+    }
+}
+
+// ==== UnsupportedMediaType ====
+
+/// An `HttpError` indicating that the `Content-Type` of the request is not supported.
+///
+/// The response is `415 Unsupported Media Type`.
+#[derive(Debug)]
+pub struct UnsupportedMediaType {
+    supported: Vec<Mime>,
+}
+
+impl UnsupportedMediaType {
+    pub(crate) fn new(supported: Vec<Mime>) -> Self {
+        UnsupportedMediaType { supported }
+    }
+
+    /// Returns the list of the supported content types.
+    pub fn supported(&self) -> &[Mime] {
+        &self.supported
+    }
+}
+
+impl fmt::Display for UnsupportedMediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unsupported media type (supported: ")?;
+        for (i, mime) in self.supported.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            fmt::Display::fmt(mime, f)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl std::error::Error for UnsupportedMediaType {}
+
+impl HttpError for UnsupportedMediaType {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
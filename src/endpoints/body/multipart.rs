@@ -0,0 +1,910 @@
+//! Parsing of `multipart/form-data` request bodies.
+//!
+//! There are two ways to receive a multipart body:
+//!
+//! * `body::multipart()` returns a `Multipart`, a `Stream` of the parts in the body.
+//!   Each `Part` is itself a `Stream` of the chunks of its content, so the large files
+//!   can be processed without buffering them in memory.
+//! * `body::multipart_form()` collects the text fields into a value of `T: Deserialize`,
+//!   and spools the uploaded files into the temporary files.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::endpoints::body::{self, multipart::Form};
+//! # use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Upload {
+//!     title: String,
+//! }
+//!
+//! let endpoint = path!(@post "/upload")
+//!     .and(body::multipart_form::<Upload>().file_limit(10 * 1024 * 1024))
+//!     .map(|form: Form<Upload>| {
+//!         for file in form.files() {
+//!             println!("{}: {}", form.fields().title, file.path().display());
+//!         }
+//!         "uploaded"
+//!     });
+//! # drop(endpoint);
+//! ```
+
+use {
+    super::{check_content_length, decompression, BodyStream, PayloadTooLarge},
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+            Preflight,
+            PreflightContext,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::{self, Error},
+    },
+    bytes::{Bytes, BytesMut},
+    futures::{Async, Poll, Stream},
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    izanami_util::buf_stream::BufStream,
+    mime::Mime,
+    serde::de::DeserializeOwned,
+    std::{
+        cell::UnsafeCell,
+        fmt,
+        fs::{self, File, OpenOptions},
+        io::{self, Write},
+        marker::PhantomData,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, MutexGuard},
+    },
+};
+
+/// The maximum length of the header block of a part.
+const MAX_HEADERS_SIZE: usize = 8 * 1024;
+
+/// The default maximum length of each text field collected by `MultipartForm`.
+const DEFAULT_FIELD_LIMIT: u64 = 64 * 1024;
+
+/// Extracts the boundary from the `Content-Type` of the request.
+fn boundary(headers: &HeaderMap) -> Result<String, Error> {
+    let mime: Mime = headers
+        .get(header::CONTENT_TYPE)
+        .ok_or_else(|| error::bad_request("missing content type"))?
+        .to_str()
+        .map_err(error::bad_request)?
+        .parse()
+        .map_err(error::bad_request)?;
+    if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+        return Err(error::bad_request(
+            "The value of `Content-type` must be `multipart/form-data`.",
+        ));
+    }
+    mime.get_param(mime::BOUNDARY)
+        .map(|boundary| boundary.as_str().to_owned())
+        .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+        .ok_or_else(|| error::bad_request("missing or invalid multipart boundary"))
+}
+
+// ==== ReceiveMultipart ====
+
+/// An endpoint which receives the request body as a stream of parts.
+///
+/// The value of this type is created by `body::multipart()`.
+#[derive(Debug)]
+pub struct ReceiveMultipart {
+    limit: Option<u64>,
+}
+
+impl ReceiveMultipart {
+    pub(super) fn new() -> Self {
+        ReceiveMultipart { limit: None }
+    }
+
+    /// Sets the maximum length of the request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        ReceiveMultipart { limit: Some(limit) }
+    }
+}
+
+impl IsEndpoint for ReceiveMultipart {}
+
+impl<Bd> Endpoint<Bd> for ReceiveMultipart
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type Output = (Multipart<Bd>,);
+    type Action = ReceiveMultipartAction<Bd>;
+
+    fn action(&self) -> Self::Action {
+        ReceiveMultipartAction {
+            limit: self.limit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[allow(missing_docs, missing_debug_implementations, clippy::type_complexity)]
+pub struct ReceiveMultipartAction<Bd> {
+    limit: Option<u64>,
+    _marker: PhantomData<(UnsafeCell<()>, fn(Bd))>,
+}
+
+impl<Bd> EndpointAction<Bd> for ReceiveMultipartAction<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type Output = (Multipart<Bd>,);
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        boundary(cx.headers())?;
+        check_content_length(self.limit, cx)?;
+        Ok(Preflight::Incomplete)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        let boundary = boundary(cx.headers())?;
+        let limit = self.limit.or_else(|| cx.body_limit());
+        let body = cx.take_body()?;
+        let body = decompression::decode(body, cx.context())?;
+        let body = BodyStream {
+            body,
+            limit,
+            received: 0,
+        };
+        Ok(Async::Ready((Multipart::new(body, &boundary),)))
+    }
+}
+
+// ==== Multipart ====
+
+/// A `Stream` of the parts in a `multipart/form-data` body.
+///
+/// The parts share the underlying request body, so the content of a part must be
+/// read before polling the next part. The unread content is skipped when the stream
+/// moves to the next part.
+pub struct Multipart<Bd> {
+    parser: Arc<Mutex<Parser<Bd>>>,
+}
+
+impl<Bd> fmt::Debug for Multipart<Bd> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart").finish()
+    }
+}
+
+impl<Bd> Multipart<Bd> {
+    fn new(body: BodyStream<Bd>, boundary: &str) -> Self {
+        // The leading CRLF allows the first delimiter to be found in the same way
+        // as the subsequent ones.
+        let mut buf = BytesMut::from(&b"\r\n"[..]);
+        buf.reserve(8192);
+        Multipart {
+            parser: Arc::new(Mutex::new(Parser {
+                body,
+                eof: false,
+                buf,
+                delimiter: format!("\r\n--{}", boundary).into(),
+                state: State::Body,
+                index: 0,
+            })),
+        }
+    }
+}
+
+impl<Bd> Stream for Multipart<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type Item = Part<Bd>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut parser = lock(&self.parser);
+        let headers = match futures::try_ready!(parser.poll_next_part()) {
+            Some(headers) => headers,
+            None => return Ok(Async::Ready(None)),
+        };
+        let part = Part::new(headers, parser.index, self.parser.clone())?;
+        Ok(Async::Ready(Some(part)))
+    }
+}
+
+fn lock<Bd>(parser: &Mutex<Parser<Bd>>) -> MutexGuard<'_, Parser<Bd>> {
+    parser
+        .lock()
+        .expect("the multipart parser has been poisoned")
+}
+
+// ==== Part ====
+
+/// A part in a `multipart/form-data` body.
+///
+/// This type is a `Stream` of the chunks of its content, which ends when the
+/// next delimiter is reached.
+pub struct Part<Bd> {
+    headers: HeaderMap,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    index: usize,
+    parser: Arc<Mutex<Parser<Bd>>>,
+}
+
+impl<Bd> fmt::Debug for Part<Bd> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("headers", &self.headers)
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+impl<Bd> Part<Bd> {
+    fn new(
+        headers: HeaderMap,
+        index: usize,
+        parser: Arc<Mutex<Parser<Bd>>>,
+    ) -> Result<Self, Error> {
+        let (name, filename) = match headers.get(header::CONTENT_DISPOSITION) {
+            Some(value) => {
+                let value = std::str::from_utf8(value.as_bytes()).map_err(error::bad_request)?;
+                parse_content_disposition(value)?
+            }
+            None => (None, None),
+        };
+        let content_type = match headers.get(header::CONTENT_TYPE) {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .map_err(error::bad_request)?
+                    .parse()
+                    .map_err(error::bad_request)?,
+            ),
+            None => None,
+        };
+        Ok(Part {
+            headers,
+            name,
+            filename,
+            content_type,
+            index,
+            parser,
+        })
+    }
+
+    /// Returns the header map of this part.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the field name specified in `Content-Disposition`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| name.as_str())
+    }
+
+    /// Returns the file name specified in `Content-Disposition`.
+    ///
+    /// The value is sent by the client as is, so it must not be used as a path
+    /// on the server without sanitizing.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_ref().map(|filename| filename.as_str())
+    }
+
+    /// Returns the value of `Content-Type` of this part.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+}
+
+impl<Bd> Stream for Part<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut parser = lock(&self.parser);
+        if parser.index != self.index {
+            return Ok(Async::Ready(None));
+        }
+        parser.poll_content()
+    }
+}
+
+/// Parses the field name and file name from the value of `Content-Disposition`.
+fn parse_content_disposition(value: &str) -> Result<(Option<String>, Option<String>), Error> {
+    let invalid = || error::bad_request("invalid Content-Disposition");
+
+    let mut rest = value;
+    let disposition = take_until(&mut rest, ';');
+    if !disposition.trim().eq_ignore_ascii_case("form-data") {
+        return Err(invalid());
+    }
+
+    let (mut name, mut filename) = (None, None);
+    while !rest.is_empty() {
+        rest = &rest[1..]; // skip ';'
+        let key = take_until(&mut rest, '=').trim();
+        if key.is_empty() && rest.is_empty() {
+            break;
+        }
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        rest = rest[1..].trim_start(); // skip '='
+
+        let value = if rest.starts_with('"') {
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => value.extend(chars.next().map(|(_, c)| c)),
+                    Some((_, c)) => value.push(c),
+                    None => return Err(invalid()),
+                }
+            };
+            rest = &rest[end..];
+            take_until(&mut rest, ';');
+            value
+        } else {
+            take_until(&mut rest, ';').trim().to_owned()
+        };
+
+        match &*key.to_ascii_lowercase() {
+            "name" => name = Some(value),
+            "filename" => filename = Some(value),
+            _ => {}
+        }
+    }
+
+    Ok((name, filename))
+}
+
+/// Returns the prefix of `s` before `delim`, and leaves `s` starting at `delim`.
+fn take_until<'a>(s: &mut &'a str, delim: char) -> &'a str {
+    let pos = s.find(delim).unwrap_or(s.len());
+    let (head, tail) = s.split_at(pos);
+    *s = tail;
+    head
+}
+
+// ==== Parser ====
+
+struct Parser<Bd> {
+    body: BodyStream<Bd>,
+    eof: bool,
+    buf: BytesMut,
+    delimiter: Bytes,
+    state: State,
+    // The index of the current part. The preamble is treated as the part 0.
+    index: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Body,
+    Delimiter,
+    Headers,
+    End,
+}
+
+impl<Bd> Parser<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    /// Reads the next chunk from the request body into the buffer.
+    fn fill(&mut self) -> Poll<(), Error> {
+        if self.eof {
+            return Err(error::bad_request("unexpected end of the multipart body"));
+        }
+        match futures::try_ready!(self.body.poll()) {
+            Some(chunk) => self.buf.extend_from_slice(&chunk),
+            None => self.eof = true,
+        }
+        Ok(Async::Ready(()))
+    }
+
+    /// Polls the next chunk of the content of the current part.
+    fn poll_content(&mut self) -> Poll<Option<Bytes>, Error> {
+        loop {
+            if self.state != State::Body {
+                return Ok(Async::Ready(None));
+            }
+
+            match find(&self.buf, &self.delimiter) {
+                Some(0) => {
+                    self.buf.advance(self.delimiter.len());
+                    self.state = State::Delimiter;
+                    return Ok(Async::Ready(None));
+                }
+                Some(pos) => return Ok(Async::Ready(Some(self.buf.split_to(pos).freeze()))),
+                // The tail of the buffer may be the beginning of the delimiter.
+                None if self.buf.len() >= self.delimiter.len() => {
+                    let len = self.buf.len() - (self.delimiter.len() - 1);
+                    return Ok(Async::Ready(Some(self.buf.split_to(len).freeze())));
+                }
+                None => futures::try_ready!(self.fill()),
+            }
+        }
+    }
+
+    /// Skips the rest of the current part and polls the headers of the next part.
+    fn poll_next_part(&mut self) -> Poll<Option<HeaderMap>, Error> {
+        loop {
+            match self.state {
+                State::Body => while futures::try_ready!(self.poll_content()).is_some() {},
+                State::Delimiter => {
+                    if self.buf.len() < 2 {
+                        futures::try_ready!(self.fill());
+                        continue;
+                    }
+                    match &self.buf[..2] {
+                        b"--" => self.state = State::End,
+                        b"\r\n" => {
+                            self.buf.advance(2);
+                            self.state = State::Headers;
+                        }
+                        _ => return Err(error::bad_request("invalid multipart delimiter")),
+                    }
+                }
+                State::Headers => {
+                    let headers = if self.buf.starts_with(b"\r\n") {
+                        self.buf.advance(2);
+                        HeaderMap::new()
+                    } else if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                        let block = self.buf.split_to(pos);
+                        self.buf.advance(4);
+                        parse_headers(&block)?
+                    } else if self.buf.len() > MAX_HEADERS_SIZE {
+                        return Err(error::bad_request(
+                            "too large header block in the multipart body",
+                        ));
+                    } else {
+                        futures::try_ready!(self.fill());
+                        continue;
+                    };
+                    self.state = State::Body;
+                    self.index += 1;
+                    return Ok(Async::Ready(Some(headers)));
+                }
+                State::End => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn parse_headers(block: &[u8]) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|&b| b == b'\n') {
+        let line = match line.last() {
+            Some(b'\r') => &line[..line.len() - 1],
+            _ => line,
+        };
+        let pos = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or_else(|| error::bad_request("invalid header in the multipart body"))?;
+        let name = HeaderName::from_bytes(&line[..pos]).map_err(error::bad_request)?;
+        let value = trim(&line[pos + 1..]);
+        let value = HeaderValue::from_bytes(value).map_err(error::bad_request)?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+fn trim(s: &[u8]) -> &[u8] {
+    let is_space = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = s.iter().position(|b| !is_space(b)).unwrap_or(s.len());
+    let end = s
+        .iter()
+        .rposition(|b| !is_space(b))
+        .map_or(start, |pos| pos + 1);
+    &s[start..end]
+}
+
+// ==== MultipartForm ====
+
+/// An endpoint which collects a `multipart/form-data` body into a `Form<T>`.
+///
+/// The value of this type is created by `body::multipart_form()`.
+pub struct MultipartForm<T> {
+    limit: Option<u64>,
+    field_limit: u64,
+    file_limit: Option<u64>,
+    temp_dir: Option<PathBuf>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> fmt::Debug for MultipartForm<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartForm")
+            .field("limit", &self.limit)
+            .field("field_limit", &self.field_limit)
+            .field("file_limit", &self.file_limit)
+            .field("temp_dir", &self.temp_dir)
+            .finish()
+    }
+}
+
+impl<T> MultipartForm<T> {
+    pub(super) fn new() -> Self {
+        MultipartForm {
+            limit: None,
+            field_limit: DEFAULT_FIELD_LIMIT,
+            file_limit: None,
+            temp_dir: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the maximum length of the whole request body, overriding `App::body_limit`.
+    pub fn limit(self, limit: u64) -> Self {
+        MultipartForm {
+            limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Sets the maximum length of each text field.
+    ///
+    /// The text fields are buffered in memory, so the limit is applied even if
+    /// the whole body is not limited. The default value is 64 KiB.
+    pub fn field_limit(self, field_limit: u64) -> Self {
+        MultipartForm {
+            field_limit,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of each uploaded file.
+    pub fn file_limit(self, file_limit: u64) -> Self {
+        MultipartForm {
+            file_limit: Some(file_limit),
+            ..self
+        }
+    }
+
+    /// Sets the directory where the uploaded files are spooled.
+    ///
+    /// The default value is `std::env::temp_dir()`.
+    pub fn temp_dir(self, temp_dir: impl Into<PathBuf>) -> Self {
+        MultipartForm {
+            temp_dir: Some(temp_dir.into()),
+            ..self
+        }
+    }
+}
+
+impl<T: DeserializeOwned> IsEndpoint for MultipartForm<T> {}
+
+impl<T, Bd> Endpoint<Bd> for MultipartForm<T>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    T: DeserializeOwned,
+{
+    type Output = (Form<T>,);
+    type Action = MultipartFormAction<Bd, T>;
+
+    fn action(&self) -> Self::Action {
+        MultipartFormAction {
+            receive: ReceiveMultipartAction {
+                limit: self.limit,
+                _marker: PhantomData,
+            },
+            field_limit: self.field_limit,
+            file_limit: self.file_limit,
+            temp_dir: self.temp_dir.clone().unwrap_or_else(std::env::temp_dir),
+            multipart: None,
+            current: None,
+            fields: vec![],
+            files: vec![],
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[allow(missing_docs, missing_debug_implementations)]
+pub struct MultipartFormAction<Bd, T> {
+    receive: ReceiveMultipartAction<Bd>,
+    field_limit: u64,
+    file_limit: Option<u64>,
+    temp_dir: PathBuf,
+    multipart: Option<Multipart<Bd>>,
+    current: Option<Current<Bd>>,
+    fields: Vec<(String, String)>,
+    files: Vec<UploadedFile>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The part being received by `MultipartFormAction`.
+enum Current<Bd> {
+    Field(Part<Bd>, Vec<u8>),
+    File(Part<Bd>, SpooledFile),
+}
+
+/// An uploaded file being written to the temporary file.
+struct SpooledFile {
+    file: Option<File>,
+    chunk: Option<Bytes>,
+    uploaded: UploadedFile,
+}
+
+impl<Bd> Current<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    fn poll_complete(&mut self, field_limit: u64, file_limit: Option<u64>) -> Poll<(), Error> {
+        match self {
+            Current::Field(part, buf) => {
+                while let Some(chunk) = futures::try_ready!(part.poll()) {
+                    if (buf.len() + chunk.len()) as u64 > field_limit {
+                        return Err(PayloadTooLarge::new(field_limit).into());
+                    }
+                    buf.extend_from_slice(&chunk);
+                }
+            }
+            Current::File(part, spooled) => loop {
+                futures::try_ready!(spooled.poll_flush().map_err(internal_server_error));
+                match futures::try_ready!(part.poll()) {
+                    Some(chunk) => {
+                        spooled.uploaded.size += chunk.len() as u64;
+                        if let Some(limit) = file_limit {
+                            if spooled.uploaded.size > limit {
+                                return Err(PayloadTooLarge::new(limit).into());
+                            }
+                        }
+                        spooled.chunk = Some(chunk);
+                    }
+                    None => break,
+                }
+            },
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl SpooledFile {
+    /// Creates the temporary file if it has not been created, and writes the pending chunk into it.
+    fn poll_flush(&mut self) -> Poll<(), io::Error> {
+        if self.file.is_none() {
+            let path = &self.uploaded.path;
+            let file = futures::try_ready!(blocking_io(|| OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)));
+            self.file = Some(file);
+        }
+        if let Some(ref chunk) = self.chunk {
+            let file = self.file.as_mut().expect("the file should be opened");
+            futures::try_ready!(blocking_io(|| file.write_all(chunk)));
+            self.chunk = None;
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+/// Runs a blocking file operation on the current thread pool.
+///
+/// The operation is run in place if the task is not on a thread pool,
+/// e.g. when it is spawned on the current-thread runtime.
+fn blocking_io<T>(f: impl FnOnce() -> io::Result<T>) -> Poll<T, io::Error> {
+    let mut f = Some(f);
+    match tokio_threadpool::blocking(|| (f.take().unwrap())()) {
+        Ok(Async::Ready(result)) => result.map(Async::Ready),
+        Ok(Async::NotReady) => Ok(Async::NotReady),
+        Err(..) => (f.take().expect("the operation has already been run"))().map(Async::Ready),
+    }
+}
+
+fn internal_server_error(err: io::Error) -> Error {
+    error::from_std(err, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+impl<Bd, T> MultipartFormAction<Bd, T> {
+    fn start(&self, part: Part<Bd>) -> Result<Current<Bd>, Error> {
+        let name = part
+            .name()
+            .ok_or_else(|| error::bad_request("missing field name in the multipart body"))?
+            .to_owned();
+        let filename = match part.filename() {
+            Some(filename) => filename.to_owned(),
+            None => return Ok(Current::Field(part, vec![])),
+        };
+
+        let uploaded = UploadedFile {
+            name,
+            filename,
+            content_type: part.content_type().cloned(),
+            path: self
+                .temp_dir
+                .join(format!("finchers-upload-{}", uuid::Uuid::new_v4())),
+            size: 0,
+            persisted: false,
+        };
+        Ok(Current::File(
+            part,
+            SpooledFile {
+                file: None,
+                chunk: None,
+                uploaded,
+            },
+        ))
+    }
+}
+
+impl<Bd, T> EndpointAction<Bd> for MultipartFormAction<Bd, T>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    T: DeserializeOwned,
+{
+    type Output = (Form<T>,);
+
+    fn preflight(
+        &mut self,
+        cx: &mut PreflightContext<'_>,
+    ) -> Result<Preflight<Self::Output>, Error> {
+        self.receive.preflight(cx).map(|_| Preflight::Incomplete)
+    }
+
+    fn poll_action(&mut self, cx: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        loop {
+            if let Some(ref mut current) = self.current {
+                futures::try_ready!(current.poll_complete(self.field_limit, self.file_limit));
+            }
+            match self.current.take() {
+                Some(Current::Field(part, buf)) => {
+                    let value = String::from_utf8(buf).map_err(error::bad_request)?;
+                    self.fields.push((part.name.unwrap_or_default(), value));
+                    continue;
+                }
+                Some(Current::File(_, spooled)) => {
+                    drop(spooled.file);
+                    self.files.push(spooled.uploaded);
+                    continue;
+                }
+                None => {}
+            }
+
+            let polled = match self.multipart {
+                Some(ref mut multipart) => futures::try_ready!(multipart.poll()),
+                None => {
+                    let (multipart,) = futures::try_ready!(self.receive.poll_action(cx));
+                    self.multipart = Some(multipart);
+                    continue;
+                }
+            };
+            match polled {
+                Some(part) => self.current = Some(self.start(part)?),
+                None => {
+                    let encoded = url::form_urlencoded::Serializer::new(String::new())
+                        .extend_pairs(&self.fields)
+                        .finish();
+                    let fields = serde_qs::from_str(&encoded)
+                        .map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST))?;
+                    let files = std::mem::replace(&mut self.files, vec![]);
+                    return Ok(Async::Ready((Form { fields, files },)));
+                }
+            }
+        }
+    }
+}
+
+// ==== Form ====
+
+/// The contents of a `multipart/form-data` body, returned from `body::multipart_form()`.
+#[derive(Debug)]
+pub struct Form<T> {
+    fields: T,
+    files: Vec<UploadedFile>,
+}
+
+impl<T> Form<T> {
+    /// Returns the value deserialized from the text fields.
+    pub fn fields(&self) -> &T {
+        &self.fields
+    }
+
+    /// Returns the list of uploaded files.
+    pub fn files(&self) -> &[UploadedFile] {
+        &self.files
+    }
+
+    /// Returns the first uploaded file with the specified field name.
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    /// Consumes `self` and returns the fields and the uploaded files.
+    pub fn into_parts(self) -> (T, Vec<UploadedFile>) {
+        (self.fields, self.files)
+    }
+}
+
+/// A file uploaded in a `multipart/form-data` body and spooled into a temporary file.
+///
+/// The temporary file is removed when this value is dropped, unless it is moved by `persist`.
+#[derive(Debug)]
+pub struct UploadedFile {
+    name: String,
+    filename: String,
+    content_type: Option<Mime>,
+    path: PathBuf,
+    size: u64,
+    persisted: bool,
+}
+
+impl UploadedFile {
+    /// Returns the field name of this file.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file name sent by the client.
+    ///
+    /// The value must not be used as a path on the server without sanitizing.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the value of `Content-Type` of this file.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Returns the path of the temporary file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of this file in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Moves the temporary file to the specified path, so that it is kept after
+    /// this value is dropped.
+    pub fn persist(mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if fs::rename(&self.path, path).is_err() {
+            // The destination may be on another file system.
+            fs::copy(&self.path, path)?;
+            return Ok(());
+        }
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for UploadedFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
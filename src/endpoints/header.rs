@@ -46,7 +46,7 @@ impl FromHeaderValue for String {
 }
 
 impl FromHeaderValue for Mime {
-    type Error = failure::Error;
+    type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
     fn from_header_value(value: &HeaderValue) -> Result<Self, Self::Error> {
         Ok(value.to_str()?.parse()?)
@@ -54,7 +54,7 @@ impl FromHeaderValue for Mime {
 }
 
 impl FromHeaderValue for Url {
-    type Error = failure::Error;
+    type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
     fn from_header_value(value: &HeaderValue) -> Result<Self, Self::Error> {
         Ok(Url::parse(value.to_str()?)?)
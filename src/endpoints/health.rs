@@ -0,0 +1,231 @@
+//! Endpoints for health checking, such as liveness and readiness probes.
+//!
+//! # Example
+//!
+//! ```
+//! # use finchers::prelude::*;
+//! # use finchers::path;
+//! # use finchers::endpoints::health::Health;
+//! # use futures::future;
+//! let liveness = Health::new();
+//! let readiness = Health::new()
+//!     .check("config", || Ok::<_, String>(()))
+//!     .check_async("database", || future::ok::<_, String>(()));
+//!
+//! let endpoint = path!(@get "/healthz").and(liveness)
+//!     .or(path!(@get "/readyz").and(readiness));
+//! # drop(endpoint);
+//! ```
+
+use {
+    crate::{
+        action::{
+            ActionContext, //
+            EndpointAction,
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+        output::IntoResponse,
+    },
+    futures::{Async, Future, IntoFuture, Poll},
+    http::{header, Request, Response, StatusCode},
+    std::{borrow::Cow, fmt, sync::Arc},
+};
+
+type CheckFuture = Box<dyn Future<Item = (), Error = String> + Send + 'static>;
+
+trait Check: Send + Sync + 'static {
+    fn run(&self) -> CheckFuture;
+}
+
+impl<F> Check for F
+where
+    F: Fn() -> CheckFuture + Send + Sync + 'static,
+{
+    fn run(&self) -> CheckFuture {
+        (*self)()
+    }
+}
+
+/// An endpoint which runs the registered checks and reports their results.
+///
+/// The checks are run concurrently for each request. The output of this endpoint
+/// is converted into a JSON response, with the status code `200 OK` if all of
+/// the checks have passed and `503 Service Unavailable` otherwise.
+///
+/// An instance without any checks always reports a healthy status, and is
+/// suitable for liveness probes.
+#[derive(Clone, Default)]
+pub struct Health {
+    checks: Vec<(Cow<'static, str>, Arc<dyn Check>)>,
+}
+
+impl fmt::Debug for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Health")
+            .field(
+                "checks",
+                &self.checks.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Health {
+    /// Creates a new `Health` without any checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a synchronous check with the specified name.
+    ///
+    /// The check fails if the function returns an `Err`, and the error message is
+    /// included in the report.
+    pub fn check<F, E>(self, name: impl Into<Cow<'static, str>>, f: F) -> Self
+    where
+        F: Fn() -> Result<(), E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.check_async(name, move || f().map_err(|e| e.to_string()))
+    }
+
+    /// Registers an asynchronous check with the specified name.
+    ///
+    /// The function is called for each request, and the check fails if the returned
+    /// future resolves to an error.
+    pub fn check_async<F, R>(mut self, name: impl Into<Cow<'static, str>>, f: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: IntoFuture<Item = ()>,
+        R::Future: Send + 'static,
+        R::Error: fmt::Display,
+    {
+        let check =
+            move || -> CheckFuture { Box::new(f().into_future().map_err(|e| e.to_string())) };
+        self.checks.push((name.into(), Arc::new(check)));
+        self
+    }
+}
+
+impl IsEndpoint for Health {}
+
+impl<Bd> Endpoint<Bd> for Health {
+    type Output = (Report,);
+    type Action = HealthAction;
+
+    fn action(&self) -> Self::Action {
+        HealthAction {
+            checks: self.checks.clone(),
+            running: None,
+        }
+    }
+}
+
+#[allow(missing_docs, missing_debug_implementations)]
+pub struct HealthAction {
+    checks: Vec<(Cow<'static, str>, Arc<dyn Check>)>,
+    running: Option<Vec<(Cow<'static, str>, CheckState)>>,
+}
+
+enum CheckState {
+    Running(CheckFuture),
+    Done(Result<(), String>),
+}
+
+impl<Bd> EndpointAction<Bd> for HealthAction {
+    type Output = (Report,);
+
+    fn poll_action(&mut self, _: &mut ActionContext<'_, Bd>) -> Poll<Self::Output, Error> {
+        // The checks are started here rather than in `Endpoint::action`, since
+        // the action may be created even if the request is routed to another endpoint.
+        let checks = &self.checks;
+        let running = self.running.get_or_insert_with(|| {
+            checks
+                .iter()
+                .map(|(name, check)| (name.clone(), CheckState::Running(check.run())))
+                .collect()
+        });
+
+        let mut ready = true;
+        for (_, state) in running.iter_mut() {
+            if let CheckState::Running(ref mut future) = state {
+                match future.poll() {
+                    Ok(Async::NotReady) => ready = false,
+                    Ok(Async::Ready(())) => *state = CheckState::Done(Ok(())),
+                    Err(err) => *state = CheckState::Done(Err(err)),
+                }
+            }
+        }
+        if !ready {
+            return Ok(Async::NotReady);
+        }
+
+        let results = running
+            .drain(..)
+            .map(|(name, state)| match state {
+                CheckState::Done(result) => (name, result),
+                CheckState::Running(..) => unreachable!(),
+            })
+            .collect();
+        Ok(Async::Ready((Report { results },)))
+    }
+}
+
+/// The results of the checks run by `Health`.
+#[derive(Debug, Clone)]
+pub struct Report {
+    results: Vec<(Cow<'static, str>, Result<(), String>)>,
+}
+
+impl Report {
+    /// Returns `true` if all of the checks have passed.
+    pub fn is_healthy(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Returns an iterator over the names and results of the checks, in the registered order.
+    pub fn results(&self) -> impl Iterator<Item = (&str, Result<(), &str>)> {
+        self.results.iter().map(|(name, result)| {
+            (
+                &**name,
+                result.as_ref().map(|_| ()).map_err(|msg| msg.as_str()),
+            )
+        })
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let checks: serde_json::Map<String, serde_json::Value> = self
+            .results
+            .iter()
+            .map(|(name, result)| {
+                let value = match result {
+                    Ok(()) => serde_json::json!({ "status": "pass" }),
+                    Err(msg) => serde_json::json!({ "status": "fail", "error": msg }),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        serde_json::json!({
+            "status": if self.is_healthy() { "pass" } else { "fail" },
+            "checks": checks,
+        })
+    }
+}
+
+impl IntoResponse for Report {
+    type Body = String;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        let status = if self.is_healthy() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(self.to_json().to_string())
+            .expect("should be a valid response")
+    }
+}
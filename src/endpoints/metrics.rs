@@ -0,0 +1,78 @@
+//! Endpoints for exposing the collected metrics.
+
+use {
+    crate::{
+        action::{
+            Oneshot,
+            OneshotAction,
+            PreflightContext, //
+        },
+        endpoint::{Endpoint, IsEndpoint},
+        error::Error,
+        service::metrics::Metrics,
+    },
+    http::{header, Response},
+};
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Create an endpoint which renders the metrics in the Prometheus text exposition format.
+///
+/// The registry must be the same one as passed to `App::metrics`.
+///
+/// # Example
+///
+/// ```
+/// # use finchers::prelude::*;
+/// # use finchers::path;
+/// # use finchers::service::metrics::Metrics;
+/// let metrics = Metrics::new();
+/// let endpoint = path!(@get "/metrics")
+///     .and(endpoints::metrics::render(metrics.clone()));
+/// # drop(endpoint);
+/// ```
+pub fn render(metrics: Metrics) -> Render {
+    Render { metrics }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct Render {
+    metrics: Metrics,
+}
+
+mod render {
+    use super::*;
+
+    impl IsEndpoint for Render {}
+
+    impl<Bd> Endpoint<Bd> for Render {
+        type Output = (Response<String>,);
+        type Action = Oneshot<RenderAction>;
+
+        fn action(&self) -> Self::Action {
+            RenderAction {
+                metrics: self.metrics.clone(),
+            }
+            .into_action()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RenderAction {
+        metrics: Metrics,
+    }
+
+    impl OneshotAction for RenderAction {
+        type Output = (Response<String>,);
+
+        fn preflight(self, _: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+            let response = Response::builder()
+                .header(header::CONTENT_TYPE, CONTENT_TYPE)
+                .body(self.metrics.render())
+                .expect("should be a valid response");
+            Ok((response,))
+        }
+    }
+}
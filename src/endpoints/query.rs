@@ -10,7 +10,7 @@ use {
         endpoint::{Endpoint, IsEndpoint},
         error::{self, Error},
     },
-    failure::SyncFailure,
+    http::StatusCode,
     serde::de::DeserializeOwned,
     std::marker::PhantomData,
 };
@@ -98,7 +98,7 @@ mod required {
                 .ok_or_else(|| error::bad_request("missing query"))?;
             serde_qs::from_str(query)
                 .map(|x| (x,))
-                .map_err(|e| error::bad_request(SyncFailure::new(e)))
+                .map_err(|e| error::from_std(e, StatusCode::BAD_REQUEST))
         }
     }
 }
@@ -183,8 +183,7 @@ mod optional {
             match cx.uri().query() {
                 Some(query) => serde_qs::from_str(query)
                     .map(|x| (Some(x),))
-                    .map_err(|err| error::bad_request(SyncFailure::new(err)))
-                    .map_err(Into::into),
+                    .map_err(|err| error::from_std(err, StatusCode::BAD_REQUEST)),
                 None => Ok((None,)),
             }
         }
@@ -0,0 +1,659 @@
+//! Components for rate limiting, used by `EndpointExt::rate_limit`.
+//!
+//! The requests are grouped by the key extracted from the request context, such as
+//! the IP address of the client or the value of an API key header. The number of
+//! requests for each key is restricted by a `Policy`, and the state of each key is
+//! recorded in a `Store`. The requests exceeding the limit are rejected with
+//! `429 Too Many Requests` and the `Retry-After` header.
+//!
+//! The responses also contain the `RateLimit-Limit`, `RateLimit-Remaining` and
+//! `RateLimit-Reset` headers for informing the clients of the current quota.
+//!
+//! # Example
+//!
+//! ```
+//! # use finchers::prelude::*;
+//! # use finchers::path;
+//! use finchers::endpoints::rate_limit::{self, Policy};
+//! use std::time::Duration;
+//!
+//! // Up to 100 requests per minute for each API key.
+//! let api = path!(@get "/api/posts")
+//!     .map(|| "posts")
+//!     .rate_limit(
+//!         rate_limit::by_header("x-api-key"),
+//!         Policy::sliding_window(100, Duration::from_secs(60)),
+//!     );
+//!
+//! // Bursts of 10 requests, refilled every 100 milliseconds, for each client IP.
+//! let login = path!(@post "/login")
+//!     .map(|| "logged in")
+//!     .rate_limit(
+//!         rate_limit::by_remote_addr(),
+//!         Policy::token_bucket(10, Duration::from_millis(100)),
+//!     );
+//!
+//! let endpoint = api.or(login);
+//! # drop(endpoint);
+//! ```
+
+use {
+    crate::{
+        error::{Error, HttpError},
+        service::Context,
+        util::ceil_secs,
+    },
+    futures::{future, Future},
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        HttpTryFrom, Request, Response, StatusCode,
+    },
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+// ==== KeyExtractor ====
+
+/// Trait representing the extraction of the key used for grouping the requests.
+///
+/// The requests whose key is not available (i.e. `extract` returns `None`) are not limited.
+pub trait KeyExtractor {
+    /// Extracts the key from the request context.
+    fn extract(&self, cx: &Context) -> Option<String>;
+}
+
+impl<F> KeyExtractor for F
+where
+    F: Fn(&Context) -> Option<String>,
+{
+    fn extract(&self, cx: &Context) -> Option<String> {
+        (*self)(cx)
+    }
+}
+
+/// Creates a `KeyExtractor` which groups the requests by the IP address of the client.
+pub fn by_remote_addr() -> ByRemoteAddr {
+    ByRemoteAddr(())
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct ByRemoteAddr(());
+
+impl KeyExtractor for ByRemoteAddr {
+    fn extract(&self, cx: &Context) -> Option<String> {
+        cx.remote_addr().map(|addr| addr.ip().to_string())
+    }
+}
+
+/// Creates a `KeyExtractor` which groups the requests by the value of the specified header.
+///
+/// # Panics
+///
+/// This function panics if the specified header name is invalid.
+pub fn by_header<H>(name: H) -> ByHeader
+where
+    HeaderName: HttpTryFrom<H>,
+    <HeaderName as HttpTryFrom<H>>::Error: fmt::Debug,
+{
+    ByHeader {
+        name: HeaderName::try_from(name).expect("invalid header name"),
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct ByHeader {
+    name: HeaderName,
+}
+
+impl KeyExtractor for ByHeader {
+    fn extract(&self, cx: &Context) -> Option<String> {
+        cx.headers()
+            .get(&self.name)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned)
+    }
+}
+
+/// Creates a `KeyExtractor` which groups the requests by the route template, e.g. `/posts/<i32>`.
+///
+/// The requests which did not match to any `path!()` are not limited.
+pub fn by_route() -> ByRoute {
+    ByRoute(())
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct ByRoute(());
+
+impl KeyExtractor for ByRoute {
+    fn extract(&self, cx: &Context) -> Option<String> {
+        cx.route().map(ToOwned::to_owned)
+    }
+}
+
+// ==== Policy ====
+
+/// The algorithm and parameters of rate limiting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Policy {
+    /// The token bucket, which allows bursts of up to `capacity` requests and
+    /// adds a token every `refill_interval`.
+    TokenBucket {
+        /// The maximum number of tokens in the bucket.
+        capacity: u64,
+        /// The interval at which a token is added to the bucket.
+        refill_interval: Duration,
+    },
+
+    /// The sliding window, which allows up to `limit` requests within any period of `window`.
+    ///
+    /// The number of requests in the window is approximated from the counts of
+    /// the current and the previous fixed windows.
+    SlidingWindow {
+        /// The maximum number of requests in a window.
+        limit: u64,
+        /// The length of the window.
+        window: Duration,
+    },
+}
+
+impl Policy {
+    /// Creates a token bucket policy.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `refill_interval` is zero.
+    pub fn token_bucket(capacity: u64, refill_interval: Duration) -> Self {
+        assert!(
+            refill_interval > Duration::from_secs(0),
+            "the refill interval must be greater than zero"
+        );
+        Policy::TokenBucket {
+            capacity,
+            refill_interval,
+        }
+    }
+
+    /// Creates a sliding window policy.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `window` is zero.
+    pub fn sliding_window(limit: u64, window: Duration) -> Self {
+        assert!(
+            window > Duration::from_secs(0),
+            "the length of the window must be greater than zero"
+        );
+        Policy::SlidingWindow { limit, window }
+    }
+
+    /// Returns the maximum number of requests allowed in a burst.
+    pub fn limit(&self) -> u64 {
+        match *self {
+            Policy::TokenBucket { capacity, .. } => capacity,
+            Policy::SlidingWindow { limit, .. } => limit,
+        }
+    }
+}
+
+// ==== Decision ====
+
+/// The result of checking the quota of a key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Decision {
+    allowed: bool,
+    limit: u64,
+    remaining: u64,
+    reset: Duration,
+    retry_after: Duration,
+}
+
+impl Decision {
+    /// Creates a `Decision` which allows the request.
+    ///
+    /// `reset` is the duration until the quota is completely restored.
+    pub fn allowed(limit: u64, remaining: u64, reset: Duration) -> Self {
+        Decision {
+            allowed: true,
+            limit,
+            remaining,
+            reset,
+            retry_after: Duration::from_secs(0),
+        }
+    }
+
+    /// Creates a `Decision` which rejects the request.
+    ///
+    /// `retry_after` is the duration until the next request will be allowed.
+    pub fn denied(limit: u64, reset: Duration, retry_after: Duration) -> Self {
+        Decision {
+            allowed: false,
+            limit,
+            remaining: 0,
+            reset,
+            retry_after,
+        }
+    }
+
+    /// Returns whether the request is allowed.
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Returns the maximum number of requests allowed in a burst.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Returns the number of requests which can be sent immediately.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Returns the duration until the quota is completely restored.
+    pub fn reset(&self) -> Duration {
+        self.reset
+    }
+
+    /// Returns the duration until the next request will be allowed, if rejected.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    pub(crate) fn insert_headers(&self, headers: &mut HeaderMap) {
+        headers.insert("ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert("ratelimit-reset", HeaderValue::from(ceil_secs(self.reset)));
+    }
+}
+
+// ==== Store ====
+
+/// Trait representing the storage of the rate limiting state.
+///
+/// This trait can be implemented for the external stores such as Redis, in order
+/// to share the quota among multiple server instances.
+pub trait Store {
+    /// The type of future returned from `check`.
+    type Future: Future<Item = Decision, Error = Error>;
+
+    /// Consumes the quota of the specified key for a request and returns the result.
+    fn check(&self, key: &str, policy: &Policy) -> Self::Future;
+}
+
+/// A `Store` which holds the state in the memory of the current process.
+///
+/// The state of the keys whose quota has been completely restored is discarded periodically.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    inner: Mutex<MemoryStoreInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryStoreInner {
+    entries: HashMap<String, Entry>,
+    checks: usize,
+}
+
+#[derive(Debug)]
+enum Entry {
+    TokenBucket {
+        tokens: f64,
+        updated: Instant,
+    },
+    SlidingWindow {
+        started: Instant,
+        current: u64,
+        previous: u64,
+    },
+}
+
+/// The number of checks between the removals of the idle entries.
+const PRUNE_INTERVAL: usize = 1024;
+
+impl MemoryStore {
+    /// Creates a new empty `MemoryStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn check_at(&self, key: &str, policy: &Policy, now: Instant) -> Decision {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        inner.checks += 1;
+        if inner.checks >= PRUNE_INTERVAL {
+            inner.checks = 0;
+            inner.entries.retain(|_, entry| !entry.is_idle(policy, now));
+        }
+
+        let entry = inner
+            .entries
+            .entry(key.to_owned())
+            .or_insert_with(|| Entry::new(policy, now));
+        entry.check(policy, now)
+    }
+}
+
+impl Store for MemoryStore {
+    type Future = future::FutureResult<Decision, Error>;
+
+    fn check(&self, key: &str, policy: &Policy) -> Self::Future {
+        future::ok(self.check_at(key, policy, Instant::now()))
+    }
+}
+
+impl Entry {
+    fn new(policy: &Policy, now: Instant) -> Self {
+        match *policy {
+            Policy::TokenBucket { capacity, .. } => Entry::TokenBucket {
+                tokens: capacity as f64,
+                updated: now,
+            },
+            Policy::SlidingWindow { .. } => Entry::SlidingWindow {
+                started: now,
+                current: 0,
+                previous: 0,
+            },
+        }
+    }
+
+    fn is_idle(&self, policy: &Policy, now: Instant) -> bool {
+        match (self, policy) {
+            (
+                Entry::TokenBucket { tokens, updated },
+                Policy::TokenBucket {
+                    capacity,
+                    refill_interval,
+                },
+            ) => {
+                let refilled = elapsed_in(now - *updated, *refill_interval);
+                tokens + refilled >= *capacity as f64
+            }
+            (Entry::SlidingWindow { started, .. }, Policy::SlidingWindow { window, .. }) => {
+                has_elapsed(now - *started, *window, 2)
+            }
+            _ => true,
+        }
+    }
+
+    fn check(&mut self, policy: &Policy, now: Instant) -> Decision {
+        if let Entry::TokenBucket { .. } = *self {
+            if let Policy::SlidingWindow { .. } = *policy {
+                *self = Entry::new(policy, now);
+            }
+        } else if let Policy::TokenBucket { .. } = *policy {
+            *self = Entry::new(policy, now);
+        }
+
+        match (self, *policy) {
+            (
+                Entry::TokenBucket { tokens, updated },
+                Policy::TokenBucket {
+                    capacity,
+                    refill_interval,
+                },
+            ) => {
+                let capacity_f = capacity as f64;
+                *tokens = (*tokens + elapsed_in(now - *updated, refill_interval)).min(capacity_f);
+                *updated = now;
+
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    let reset = mul_duration(refill_interval, capacity_f - *tokens);
+                    Decision::allowed(capacity, *tokens as u64, reset)
+                } else {
+                    let reset = mul_duration(refill_interval, capacity_f - *tokens);
+                    let retry_after = mul_duration(refill_interval, 1.0 - *tokens);
+                    Decision::denied(capacity, reset, retry_after)
+                }
+            }
+
+            (
+                Entry::SlidingWindow {
+                    started,
+                    current,
+                    previous,
+                },
+                Policy::SlidingWindow { limit, window },
+            ) => {
+                let mut elapsed = now - *started;
+                if has_elapsed(elapsed, window, 2) {
+                    *previous = 0;
+                    *current = 0;
+                    *started = now;
+                    elapsed = Duration::from_secs(0);
+                } else if elapsed >= window {
+                    *previous = *current;
+                    *current = 0;
+                    *started += window;
+                    elapsed -= window;
+                }
+
+                // The weight of the previous window in the sliding window.
+                let weight = (1.0 - elapsed_in(elapsed, window)).max(0.0);
+                let count = *previous as f64 * weight + *current as f64;
+                let until_next_window = window - elapsed;
+                let reset = until_next_window
+                    .checked_add(mul_duration(window, weight))
+                    .unwrap_or_else(max_duration);
+
+                if count + 1.0 <= limit as f64 {
+                    *current += 1;
+                    let remaining = (limit as f64 - count - 1.0).floor() as u64;
+                    Decision::allowed(limit, remaining, reset)
+                } else {
+                    let retry_after = if *current < limit && *previous > 0 {
+                        // Waits until the weight of the previous window decreases enough.
+                        let required = 1.0 - (limit - *current - 1) as f64 / *previous as f64;
+                        mul_duration(window, required).checked_sub(elapsed)
+                    } else {
+                        None
+                    };
+                    let retry_after = match retry_after {
+                        Some(retry_after) if retry_after > Duration::from_secs(0) => retry_after,
+                        _ => until_next_window,
+                    };
+                    Decision::denied(limit, reset, retry_after)
+                }
+            }
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Returns the duration used in place of the ones which overflow.
+fn max_duration() -> Duration {
+    Duration::from_secs(std::u64::MAX)
+}
+
+/// Returns whether `elapsed` has reached `count` times of `unit`.
+///
+/// The result is `false` if the product overflows, since no duration can reach it.
+fn has_elapsed(elapsed: Duration, unit: Duration, count: u32) -> bool {
+    match unit.checked_mul(count) {
+        Some(duration) => elapsed >= duration,
+        None => false,
+    }
+}
+
+/// Returns the number of seconds contained in the duration, as a real number.
+fn as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+/// Returns how many times `unit` is contained in `elapsed`, as a real number.
+fn elapsed_in(elapsed: Duration, unit: Duration) -> f64 {
+    if unit == Duration::from_secs(0) {
+        return std::f64::INFINITY;
+    }
+    as_secs_f64(elapsed) / as_secs_f64(unit)
+}
+
+/// Multiplies the duration by a non-negative factor, saturating at the maximum duration.
+fn mul_duration(duration: Duration, factor: f64) -> Duration {
+    let secs = (as_secs_f64(duration) * factor).max(0.0);
+    if secs >= as_secs_f64(max_duration()) {
+        return max_duration();
+    }
+    let nanos = ((secs - secs.trunc()) * 1e9) as u32;
+    Duration::new(secs.trunc() as u64, nanos.min(999_999_999))
+}
+
+// ==== RateLimited ====
+
+/// An `HttpError` indicating that the request has been rejected by the rate limiting.
+///
+/// The response is `429 Too Many Requests` with the `Retry-After` header.
+#[derive(Debug)]
+pub struct RateLimited {
+    decision: Decision,
+}
+
+impl RateLimited {
+    pub(crate) fn new(decision: Decision) -> Self {
+        RateLimited { decision }
+    }
+
+    /// Returns the result of checking the quota.
+    pub fn decision(&self) -> &Decision {
+        &self.decision
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("too many requests")
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+impl HttpError for RateLimited {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn to_response(&self, _: &Request<()>) -> Response<()> {
+        let mut response = Response::new(());
+        *response.status_mut() = self.status_code();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(ceil_secs(self.decision.retry_after)),
+        );
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let store = MemoryStore::new();
+        let policy = Policy::token_bucket(2, millis(1000));
+        let now = Instant::now();
+
+        let decision = store.check_at("a", &policy, now);
+        assert!(decision.is_allowed());
+        assert_eq!(decision.remaining(), 1);
+        assert_eq!(decision.reset(), millis(1000));
+
+        assert!(store.check_at("a", &policy, now).is_allowed());
+
+        let decision = store.check_at("a", &policy, now + millis(250));
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.retry_after(), millis(750));
+
+        // The other keys are not affected.
+        assert!(store.check_at("b", &policy, now).is_allowed());
+
+        let decision = store.check_at("a", &policy, now + millis(1000));
+        assert!(decision.is_allowed());
+        assert_eq!(decision.remaining(), 0);
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let store = MemoryStore::new();
+        let policy = Policy::sliding_window(2, millis(1000));
+        let now = Instant::now();
+
+        assert!(store.check_at("a", &policy, now).is_allowed());
+        let decision = store.check_at("a", &policy, now + millis(500));
+        assert!(decision.is_allowed());
+        assert_eq!(decision.remaining(), 0);
+
+        let decision = store.check_at("a", &policy, now + millis(600));
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.retry_after(), millis(400));
+
+        // Half of the previous window is counted: 2 * 0.5 + 0 < 2
+        let decision = store.check_at("a", &policy, now + millis(1500));
+        assert!(decision.is_allowed());
+        assert!(!store
+            .check_at("a", &policy, now + millis(1500))
+            .is_allowed());
+
+        assert!(store
+            .check_at("a", &policy, now + millis(4000))
+            .is_allowed());
+    }
+
+    #[test]
+    #[should_panic(expected = "the length of the window must be greater than zero")]
+    fn test_zero_window() {
+        Policy::sliding_window(2, millis(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "the refill interval must be greater than zero")]
+    fn test_zero_refill_interval() {
+        Policy::token_bucket(2, millis(0));
+    }
+
+    #[test]
+    fn test_extreme_durations() {
+        let store = MemoryStore::new();
+        let now = Instant::now();
+
+        // The policies constructed directly are not validated, but never break the math.
+        let policy = Policy::SlidingWindow {
+            limit: 1,
+            window: millis(0),
+        };
+        assert!(store.check_at("a", &policy, now).is_allowed());
+        assert!(store.check_at("a", &policy, now).is_allowed());
+        let policy = Policy::TokenBucket {
+            capacity: 1,
+            refill_interval: millis(0),
+        };
+        assert!(store.check_at("b", &policy, now).is_allowed());
+        assert!(store.check_at("b", &policy, now).is_allowed());
+
+        let max = Duration::new(std::u64::MAX, 999_999_999);
+        let policy = Policy::sliding_window(1, max);
+        assert!(store.check_at("c", &policy, now).is_allowed());
+        let decision = store.check_at("c", &policy, now + millis(1));
+        assert!(!decision.is_allowed());
+        assert!(!Entry::new(&policy, now).is_idle(&policy, now + millis(1)));
+
+        let policy = Policy::token_bucket(2, max);
+        assert!(store.check_at("d", &policy, now).is_allowed());
+        let decision = store.check_at("d", &policy, now);
+        assert!(decision.is_allowed());
+        assert_eq!(decision.reset(), max_duration());
+        assert!(!store.check_at("d", &policy, now).is_allowed());
+    }
+}
@@ -0,0 +1,108 @@
+//! Endpoints for extracting the ID assigned to the request.
+//!
+//! The request ID is available only if it is enabled by `App::request_id`.
+
+use crate::{
+    action::{
+        Oneshot,
+        OneshotAction,
+        PreflightContext, //
+    },
+    endpoint::{Endpoint, IsEndpoint},
+    error::{self, Error},
+};
+
+pub use crate::service::request_id::RequestId;
+
+// ==== Required ====
+
+/// Create an endpoint which extracts the ID assigned to the request.
+///
+/// If the request ID is not enabled, this endpoint will return an internal server error.
+///
+/// # Example
+///
+/// ```
+/// # use finchers::endpoints::request_id::{self, RequestId};
+/// # use finchers::prelude::*;
+/// let endpoint = request_id::required()
+///     .map(|id: RequestId| format!("request ID: {}", id));
+/// # drop(endpoint);
+/// ```
+#[inline]
+pub fn required() -> Required {
+    Required(())
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct Required(());
+
+mod required {
+    use super::*;
+
+    impl IsEndpoint for Required {}
+
+    impl<Bd> Endpoint<Bd> for Required {
+        type Output = (RequestId,);
+        type Action = Oneshot<RequiredAction>;
+
+        fn action(&self) -> Self::Action {
+            RequiredAction(()).into_action()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RequiredAction(());
+
+    impl OneshotAction for RequiredAction {
+        type Output = (RequestId,);
+
+        fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+            cx.request_id()
+                .cloned()
+                .map(|id| (id,))
+                .ok_or_else(|| error::internal_server_error("the request ID is not enabled"))
+        }
+    }
+}
+
+// ==== Optional ====
+
+/// Create an endpoint which extracts the ID assigned to the request.
+///
+/// This endpoint always matches and returns a `None` if the request ID is not enabled.
+#[inline]
+pub fn optional() -> Optional {
+    Optional(())
+}
+
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct Optional(());
+
+mod optional {
+    use super::*;
+
+    impl IsEndpoint for Optional {}
+
+    impl<Bd> Endpoint<Bd> for Optional {
+        type Output = (Option<RequestId>,);
+        type Action = Oneshot<OptionalAction>;
+
+        fn action(&self) -> Self::Action {
+            OptionalAction(()).into_action()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct OptionalAction(());
+
+    impl OneshotAction for OptionalAction {
+        type Output = (Option<RequestId>,);
+
+        fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+            Ok((cx.request_id().cloned(),))
+        }
+    }
+}
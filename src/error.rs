@@ -2,16 +2,21 @@
 
 use {
     crate::{output::IntoResponse, util::Never},
-    failure::{AsFail, Fail},
     http::{Request, Response, StatusCode},
-    std::{any::TypeId, fmt, io},
+    std::{any::TypeId, error::Error as StdError, fmt, io},
+};
+
+#[cfg(feature = "backtrace")]
+use {
+    backtrace::Backtrace,
+    std::sync::atomic::{AtomicUsize, Ordering},
 };
 
 /// Trait that abstracts the error representation used in Finchers.
 ///
 /// Roughly speaking, this trait adds some context around HTTP to
-/// `failure::Fail`.
-pub trait HttpError: AsFail + Send + Sync + 'static {
+/// `std::error::Error`.
+pub trait HttpError: StdError + AsStdError + Send + Sync + 'static {
     /// Returns an HTTP status code associated with this error value.
     fn status_code(&self) -> StatusCode {
         StatusCode::INTERNAL_SERVER_ERROR
@@ -24,6 +29,14 @@ pub trait HttpError: AsFail + Send + Sync + 'static {
         response
     }
 
+    /// Returns the backtrace captured when this error was created, if any.
+    ///
+    /// This method is available only if the feature `backtrace` is enabled.
+    #[cfg(feature = "backtrace")]
+    fn backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
     // not a public API.
     #[doc(hidden)]
     fn __private_type_id__(&self) -> TypeId {
@@ -31,6 +44,23 @@ pub trait HttpError: AsFail + Send + Sync + 'static {
     }
 }
 
+/// Trait for converting a value into a trait object of `std::error::Error`.
+///
+/// This trait is automatically implemented for all sized error types.
+pub trait AsStdError {
+    /// Converts `self` into a trait object of `std::error::Error`.
+    fn as_std_error(&self) -> &(dyn StdError + Send + Sync + 'static);
+}
+
+impl<E> AsStdError for E
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn as_std_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self
+    }
+}
+
 impl dyn HttpError {
     /// Returns `true` if the type of contained value is the same as `T`.
     pub fn is<T: HttpError>(&self) -> bool {
@@ -65,34 +95,6 @@ impl dyn HttpError {
     }
 }
 
-impl fmt::Debug for dyn HttpError {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(self.as_fail(), f)
-    }
-}
-
-impl fmt::Display for dyn HttpError {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(self.as_fail(), f)
-    }
-}
-
-impl Fail for dyn HttpError {
-    #[inline]
-    fn name(&self) -> Option<&str> {
-        self.as_fail().name()
-    }
-
-    #[inline]
-    fn cause(&self) -> Option<&dyn Fail> {
-        self.as_fail().cause()
-    }
-
-    // TODO: backtrace
-}
-
 impl HttpError for Never {
     fn status_code(&self) -> StatusCode {
         match *self {}
@@ -113,21 +115,6 @@ impl HttpError for io::Error {
     }
 }
 
-impl HttpError for failure::Error {
-    fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
-    }
-}
-
-impl<E> HttpError for failure::SyncFailure<E>
-where
-    E: std::error::Error + Send + 'static,
-{
-    fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
-    }
-}
-
 // ==== ErrorMessage ====
 
 /// Creates a value of`Error` from the specific message and status code.
@@ -135,8 +122,7 @@ pub fn err_msg<D>(msg: D, status: StatusCode) -> Error
 where
     D: fmt::Display + fmt::Debug + Send + Sync + 'static,
 {
-    #[derive(Debug, failure::Fail)]
-    #[fail(display = "{}", msg)]
+    #[derive(Debug)]
     struct ErrorMessage<D>
     where
         D: fmt::Display + fmt::Debug + Send + Sync + 'static,
@@ -145,6 +131,17 @@ where
         status: StatusCode,
     }
 
+    impl<D> fmt::Display for ErrorMessage<D>
+    where
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(&self.msg, f)
+        }
+    }
+
+    impl<D> StdError for ErrorMessage<D> where D: fmt::Display + fmt::Debug + Send + Sync + 'static {}
+
     impl<D> HttpError for ErrorMessage<D>
     where
         D: fmt::Display + fmt::Debug + Send + Sync + 'static,
@@ -180,48 +177,157 @@ define_error_fn! {
     internal_server_error => INTERNAL_SERVER_ERROR,
 }
 
-/// Creates a value of `Error` from the specific error value.
-pub fn fail<E>(error: E, status: StatusCode) -> Error
+/// Creates a value of `Error` from the specific error value and status code.
+///
+/// The error value is kept as is, so it can be retrieved later by using
+/// `Error::find_cause`.
+pub fn from_std<E>(error: E, status: StatusCode) -> Error
 where
-    E: AsFail + Send + Sync + 'static,
+    E: Into<Box<dyn StdError + Send + Sync + 'static>>,
 {
-    #[derive(Debug)]
-    struct Failure<E>
-    where
-        E: AsFail + Send + Sync + 'static,
-    {
-        error: E,
-        status: StatusCode,
+    StdErrorWithStatus {
+        error: error.into(),
+        status,
     }
+    .into()
+}
 
-    impl<E> fmt::Display for Failure<E>
-    where
-        E: AsFail + Send + Sync + 'static,
-    {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            fmt::Display::fmt(self.as_fail(), f)
+#[derive(Debug)]
+struct StdErrorWithStatus {
+    error: Box<dyn StdError + Send + Sync + 'static>,
+    status: StatusCode,
+}
+
+impl fmt::Display for StdErrorWithStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.error, f)
+    }
+}
+
+impl StdError for StdErrorWithStatus {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.source()
+    }
+}
+
+impl HttpError for StdErrorWithStatus {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+}
+
+// ==== Context ====
+
+/// An error type which adds a contextual message to an existing `Error`.
+///
+/// The status code and the response of the wrapped error are preserved,
+/// so adding a context never changes how the error is reported to the client.
+#[derive(Debug)]
+struct Context<D>
+where
+    D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    msg: D,
+    cause: Error,
+    #[cfg(feature = "backtrace")]
+    backtrace: Option<Backtrace>,
+}
+
+impl<D> fmt::Display for Context<D>
+where
+    D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.msg, f)
+    }
+}
+
+impl<D> StdError for Context<D>
+where
+    D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.cause.chain_root())
+    }
+}
+
+impl<D> HttpError for Context<D>
+where
+    D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    fn status_code(&self) -> StatusCode {
+        self.cause.status_code()
+    }
+
+    fn to_response(&self, request: &Request<()>) -> Response<()> {
+        self.cause.to_response(request)
+    }
+
+    #[cfg(feature = "backtrace")]
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.cause.backtrace().or(self.backtrace.as_ref())
+    }
+}
+
+/// Captures a backtrace if it is enabled by the environment variable `RUST_LIB_BACKTRACE`
+/// or `RUST_BACKTRACE`, in the same way as `std::backtrace::Backtrace::capture`.
+#[cfg(feature = "backtrace")]
+fn capture_backtrace() -> Option<Backtrace> {
+    // 0: not checked yet, 1: disabled, 2: enabled
+    static ENABLED: AtomicUsize = AtomicUsize::new(0);
+
+    let enabled = match ENABLED.load(Ordering::Relaxed) {
+        0 => {
+            let enabled = match std::env::var_os("RUST_LIB_BACKTRACE") {
+                Some(value) => value != "0",
+                None => std::env::var_os("RUST_BACKTRACE").map_or(false, |value| value != "0"),
+            };
+            ENABLED.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+            enabled
         }
+        state => state == 2,
+    };
+    if enabled {
+        Some(Backtrace::new())
+    } else {
+        None
     }
+}
+
+/// A set of extension methods for adding a context to the error values.
+pub trait ResultExt<T>: Sized {
+    /// Wraps the error value with the specified contextual message.
+    ///
+    /// The status code of the original error is preserved.
+    fn http_context<D>(self, msg: D) -> Result<T>
+    where
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// Wraps the error value with the contextual message lazily created by the closure.
+    fn with_http_context<F, D>(self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> D,
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static;
+}
 
-    impl<E> AsFail for Failure<E>
+impl<T, E> ResultExt<T> for std::result::Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn http_context<D>(self, msg: D) -> Result<T>
     where
-        E: AsFail + Send + Sync + 'static,
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static,
     {
-        fn as_fail(&self) -> &dyn Fail {
-            self.error.as_fail()
-        }
+        self.map_err(|err| err.into().context(msg))
     }
 
-    impl<E> HttpError for Failure<E>
+    fn with_http_context<F, D>(self, f: F) -> Result<T>
     where
-        E: AsFail + Send + Sync + 'static,
+        F: FnOnce() -> D,
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static,
     {
-        fn status_code(&self) -> StatusCode {
-            self.status
-        }
+        self.map_err(|err| err.into().context(f()))
     }
-
-    Failure { error, status }.into()
 }
 
 // ==== Error ====
@@ -270,6 +376,12 @@ impl fmt::Display for Error {
     }
 }
 
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.chain_root().source()
+    }
+}
+
 impl IntoResponse for Error {
     type Body = String;
 
@@ -289,6 +401,67 @@ impl Error {
         }
     }
 
+    /// Wraps this error value with the specified contextual message.
+    ///
+    /// The returned error has the same status code as `self`, and `self` is
+    /// accessible as its source. If the feature `backtrace` is enabled and the backtrace
+    /// is enabled by the environment variable `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`,
+    /// the backtrace is captured at this point.
+    pub fn context<D>(self, msg: D) -> Self
+    where
+        D: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        Self::new(Context {
+            msg,
+            cause: self,
+            #[cfg(feature = "backtrace")]
+            backtrace: capture_backtrace(),
+        })
+    }
+
+    /// Returns an iterator over the chain of causes, starting with this error itself.
+    ///
+    /// This is mainly useful for logging the whole context of an error.
+    pub fn iter_chain(&self) -> Chain<'_> {
+        Chain {
+            next: Some(self.chain_root()),
+        }
+    }
+
+    /// Returns the cause at the bottom of the chain.
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.iter_chain()
+            .last()
+            .expect("the chain always contains at least one error")
+    }
+
+    /// Returns the first cause in the chain whose type is `T`, if any.
+    ///
+    /// Unlike `downcast`, this method can also retrieve the error values
+    /// which do not implement `HttpError`, such as the ones passed to `from_std`.
+    pub fn find_cause<T>(&self) -> Option<&T>
+    where
+        T: StdError + 'static,
+    {
+        self.iter_chain()
+            .find_map(|cause| cause.downcast_ref::<T>())
+    }
+
+    /// Returns the innermost backtrace captured in the chain of causes, if any.
+    ///
+    /// This method is available only if the feature `backtrace` is enabled.
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
+    }
+
+    fn chain_root(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        match self.inner.downcast_ref::<StdErrorWithStatus>() {
+            Some(wrapped) => &*wrapped.error,
+            None => self.inner.as_std_error(),
+        }
+    }
+
     /// Attempts to downcast the boxed value to a conrete type.
     pub fn downcast<T: HttpError>(self) -> Result<T> {
         self.inner
@@ -309,5 +482,21 @@ impl Error {
     }
 }
 
+/// An iterator over the chain of causes of an `Error`.
+#[derive(Debug)]
+pub struct Chain<'a> {
+    next: Option<&'a (dyn StdError + 'static)>,
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn StdError + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cur = self.next.take()?;
+        self.next = cur.source();
+        Some(cur)
+    }
+}
+
 /// A type alias of `Result<T, E>` whose error type is restricted to `Error`.
 pub type Result<T> = std::result::Result<T, Error>;
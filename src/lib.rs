@@ -10,11 +10,11 @@
 //!
 //! # Example
 //!
-//! ```
+//! ```no_run
 //! use finchers::prelude::*;
 //! use finchers::endpoint::syntax::path;
 //!
-//! # fn main() -> izanami::Result<()> {
+//! # fn main() -> Result<(), finchers::server::ServerError> {
 //! let get_post = path!(@get "/<u64>")
 //!     .map(|id: u64| format!("GET: id={}", id));
 //!
@@ -25,11 +25,8 @@
 //! let endpoint = path!("/posts")
 //!     .and(get_post.or(create_post));
 //!
-//! # drop(move || -> izanami::Result<_> {
-//! izanami::Server::bind_tcp(&"127.0.0.1:4000".parse()?)?
-//!     .start(endpoint.into_service())
-//! # });
-//! # Ok(())
+//! finchers::server::start(endpoint)
+//!     .serve("127.0.0.1:4000")
 //! # }
 //! ```
 
@@ -52,6 +49,7 @@ pub mod endpoint;
 pub mod endpoints;
 pub mod error;
 pub mod output;
+pub mod server;
 pub mod service;
 pub mod test;
 pub mod util;
@@ -4,18 +4,30 @@ pub mod fs;
 pub mod status;
 
 mod binary;
+#[cfg(feature = "cbor")]
+mod cbor;
 mod debug;
 mod json;
+#[cfg(feature = "msgpack")]
+mod msgpack;
 mod redirect;
 mod text;
+#[cfg(feature = "xml")]
+mod xml;
 
 use either::Either;
 use http::{Request, Response, StatusCode};
 
+#[cfg(feature = "cbor")]
+pub use self::cbor::Cbor;
 pub use self::debug::Debug;
 pub use self::fs::NamedFile;
 pub use self::json::Json;
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::MsgPack;
 pub use self::redirect::Redirect;
+#[cfg(feature = "xml")]
+pub use self::xml::Xml;
 
 /// A trait representing the value to be converted into an HTTP response.
 pub trait IntoResponse {
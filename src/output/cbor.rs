@@ -0,0 +1,50 @@
+use http::header::HeaderValue;
+use http::{header, Request, Response, StatusCode};
+use serde::Serialize;
+
+use super::IntoResponse;
+
+/// An instance of `Output` representing statically typed CBOR responses.
+///
+/// This type is available only if the feature `cbor` is enabled.
+#[derive(Debug)]
+pub struct Cbor<T>(pub T);
+
+impl<T> From<T> for Cbor<T> {
+    #[inline]
+    fn from(inner: T) -> Self {
+        Cbor(inner)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    code: u16,
+    message: String,
+}
+
+impl<T: Serialize> IntoResponse for Cbor<T> {
+    type Body = Vec<u8>;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        let (status, body) = match serde_cbor::to_vec(&self.0) {
+            Ok(body) => (StatusCode::OK, body),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_cbor::to_vec(&ErrorMessage {
+                    code: 500,
+                    message: format!("failed to construct CBOR response: {}", err),
+                })
+                .unwrap_or_default(),
+            ),
+        };
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/cbor"),
+        );
+        response
+    }
+}
@@ -0,0 +1,52 @@
+use http::header::HeaderValue;
+use http::{header, Request, Response, StatusCode};
+use serde::Serialize;
+
+use super::IntoResponse;
+
+/// An instance of `Output` representing statically typed MessagePack responses.
+///
+/// The structs are serialized as maps with the field names, as in the JSON responses.
+///
+/// This type is available only if the feature `msgpack` is enabled.
+#[derive(Debug)]
+pub struct MsgPack<T>(pub T);
+
+impl<T> From<T> for MsgPack<T> {
+    #[inline]
+    fn from(inner: T) -> Self {
+        MsgPack(inner)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    code: u16,
+    message: String,
+}
+
+impl<T: Serialize> IntoResponse for MsgPack<T> {
+    type Body = Vec<u8>;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        let (status, body) = match rmp_serde::to_vec_named(&self.0) {
+            Ok(body) => (StatusCode::OK, body),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                rmp_serde::to_vec_named(&ErrorMessage {
+                    code: 500,
+                    message: format!("failed to construct MessagePack response: {}", err),
+                })
+                .unwrap_or_default(),
+            ),
+        };
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/msgpack"),
+        );
+        response
+    }
+}
@@ -0,0 +1,53 @@
+use http::header::HeaderValue;
+use http::{header, Request, Response, StatusCode};
+use serde::Serialize;
+
+use super::IntoResponse;
+
+/// An instance of `Output` representing statically typed XML responses.
+///
+/// The root element is named after the type of the value.
+///
+/// This type is available only if the feature `xml` is enabled.
+#[derive(Debug)]
+pub struct Xml<T>(pub T);
+
+impl<T> From<T> for Xml<T> {
+    #[inline]
+    fn from(inner: T) -> Self {
+        Xml(inner)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "error")]
+struct ErrorMessage {
+    code: u16,
+    message: String,
+}
+
+impl<T: Serialize> IntoResponse for Xml<T> {
+    type Body = String;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        let (status, body) = match serde_xml_rs::to_string(&self.0) {
+            Ok(body) => (StatusCode::OK, body),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_xml_rs::to_string(&ErrorMessage {
+                    code: 500,
+                    message: format!("failed to construct XML response: {}", err),
+                })
+                .unwrap_or_default(),
+            ),
+        };
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml; charset=utf-8"),
+        );
+        response
+    }
+}
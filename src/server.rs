@@ -0,0 +1,76 @@
+//! The built-in HTTP server for running the endpoints.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::server::RuntimeMode;
+//!
+//! # fn main() -> Result<(), finchers::server::ServerError> {
+//! let endpoint = path!(@get "/").map(|| "Hello, world!");
+//!
+//! finchers::server::start(endpoint)
+//!     .keep_alive(false)
+//!     .runtime(RuntimeMode::CurrentThread)
+//!     .serve("127.0.0.1:4000")
+//! # }
+//! ```
+//!
+//! The services configured with `App` can be started with `Server::new`:
+//!
+//! ```no_run
+//! # use finchers::prelude::*;
+//! # use finchers::path;
+//! use finchers::server::Server;
+//! use finchers::service::{access_log::AccessLog, App};
+//!
+//! # fn main() -> Result<(), finchers::server::ServerError> {
+//! # let endpoint = path!(@get "/").map(|| "Hello, world!");
+//! let app = App::new(endpoint).access_log(AccessLog::common());
+//! Server::new(app).serve("127.0.0.1:4000")
+//! # }
+//! ```
+//!
+//! # Graceful shutdown
+//!
+//! ```no_run
+//! # use finchers::prelude::*;
+//! # use finchers::path;
+//! use futures::sync::oneshot;
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<(), finchers::server::ServerError> {
+//! # let endpoint = path!(@get "/").map(|| "Hello, world!");
+//! let (tx, rx) = oneshot::channel::<()>();
+//! // `tx` is sent, e.g., from the handler of SIGTERM.
+//! # drop(tx);
+//!
+//! let server = finchers::server::start(endpoint)
+//!     .shutdown_signal(rx)
+//!     .shutdown_timeout(Duration::from_secs(10));
+//! let in_flight = server.in_flight_requests();
+//! # drop(in_flight);
+//! server.serve("127.0.0.1:4000")
+//! # }
+//! ```
+
+mod builder;
+mod error;
+mod http_server;
+mod listener;
+mod shutdown;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(unix)]
+mod unix;
+
+pub use self::{
+    builder::{start, RuntimeMode, Server},
+    error::{ServerError, ServerResult},
+    http_server::RequestBody,
+    shutdown::InFlightRequests,
+};
+
+#[cfg(unix)]
+pub use self::unix::UnixSocket;
@@ -1,72 +1,296 @@
-use std::error;
+use {
+    super::{
+        error::{ServerError, ServerResult},
+        http_server::{Graceful, RequestBody, Serve},
+        listener::{Listener, TcpIncoming},
+        shutdown::{Counter, InFlightRequests, ShutdownSignal},
+    },
+    crate::service::{App, ConnectionInfo},
+    futures::{future, Future},
+    http::{Request, Response},
+    hyper::server::conn::Http,
+    izanami_service::{MakeService, Service},
+    izanami_util::buf_stream::BufStream,
+    std::{error, io, net::ToSocketAddrs, sync::Arc, time::Duration},
+};
 
-use futures::{future, Future};
-use http::{Request, Response};
-use hyper::body::{Body, Payload};
-use tower_service;
-use tower_service::NewService;
+#[cfg(feature = "tls")]
+use super::tls::{TlsConfig, TlsIncoming};
+#[cfg(unix)]
+use super::unix::{UnixIncoming, UnixSocket};
 
-use super::error::{ServerError, ServerResult};
-use super::http_server::ServerConfig;
+/// Create a builder of the HTTP server which serves the specified endpoint.
+///
+/// This function is a shortcut of `Server::new(App::new(endpoint))`.
+pub fn start<E>(endpoint: E) -> Server<App<E>> {
+    Server::new(App::new(endpoint))
+}
 
-/// A builder of HTTP service.
-#[derive(Debug)]
-pub struct ServiceBuilder<S> {
-    new_service: S,
+/// The kind of Tokio runtime used for running the server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RuntimeMode {
+    /// The multi-threaded runtime backed by a thread pool.
+    ThreadPool,
+    /// The single-threaded runtime, which runs all tasks on the current thread.
+    CurrentThread,
 }
 
-impl<S> ServiceBuilder<S>
-where
-    S: NewService,
-{
-    /// Creates a new `ServerBuilder` from the specified NewService.
-    pub fn new(new_service: S) -> Self {
-        ServiceBuilder { new_service }
+impl Default for RuntimeMode {
+    fn default() -> Self {
+        RuntimeMode::ThreadPool
     }
 }
 
-impl<S> NewService for ServiceBuilder<S>
-where
-    S: NewService,
-{
-    type Request = S::Request;
-    type Response = S::Response;
-    type Error = S::Error;
-    type Service = S::Service;
-    type InitError = S::InitError;
-    type Future = S::Future;
+/// A builder of the HTTP server.
+#[derive(Debug)]
+pub struct Server<S> {
+    make_service: S,
+    keep_alive: bool,
+    http1: bool,
+    http2: bool,
+    runtime: RuntimeMode,
+    shutdown_signal: Option<ShutdownSignal>,
+    shutdown_timeout: Duration,
+    in_flight: Arc<Counter>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+impl<S> Server<S> {
+    /// Creates a new `Server` from the specified service factory, such as `App`.
+    pub fn new(make_service: S) -> Self {
+        Server {
+            make_service,
+            keep_alive: true,
+            http1: true,
+            http2: true,
+            runtime: RuntimeMode::default(),
+            shutdown_signal: None,
+            shutdown_timeout: Duration::from_secs(30),
+            in_flight: Arc::new(Counter::default()),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Sets whether to keep the HTTP/1 connections alive between requests.
+    ///
+    /// The default value is `true`.
+    pub fn keep_alive(self, enabled: bool) -> Self {
+        Server {
+            keep_alive: enabled,
+            ..self
+        }
+    }
+
+    /// Sets whether to accept the connections using HTTP/1.
+    ///
+    /// The default value is `true`.
+    pub fn http1(self, enabled: bool) -> Self {
+        Server {
+            http1: enabled,
+            ..self
+        }
+    }
+
+    /// Sets whether to accept the connections using HTTP/2 with prior knowledge.
+    ///
+    /// When both HTTP/1 and HTTP/2 are enabled, the protocol of each connection is
+    /// detected from the connection preface sent by the client.
+    ///
+    /// The default value is `true`.
+    pub fn http2(self, enabled: bool) -> Self {
+        Server {
+            http2: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the kind of runtime used for running the server.
+    ///
+    /// The default value is `RuntimeMode::ThreadPool`.
+    pub fn runtime(self, runtime: RuntimeMode) -> Self {
+        Server { runtime, ..self }
+    }
+
+    /// Sets the future which triggers the graceful shutdown of the server when completed.
+    ///
+    /// After the signal is fired, the server stops accepting new connections and
+    /// waits for the requests in progress to be completed, and then `serve` returns.
+    /// The connections kept alive are closed after sending the current response.
+    ///
+    /// The result of the future is ignored, so the shutdown is also triggered
+    /// when the future fails.
+    pub fn shutdown_signal<F>(self, signal: F) -> Self
+    where
+        F: Future + Send + 'static,
+    {
+        Server {
+            shutdown_signal: Some(ShutdownSignal::new(signal)),
+            ..self
+        }
+    }
 
-    #[inline]
-    fn new_service(&self) -> Self::Future {
-        self.new_service.new_service()
+    /// Sets the maximum duration to wait for the requests in progress during the graceful shutdown.
+    ///
+    /// The remaining connections are forcibly closed after the timeout has elapsed.
+    ///
+    /// The default value is 30 seconds.
+    pub fn shutdown_timeout(self, timeout: Duration) -> Self {
+        Server {
+            shutdown_timeout: timeout,
+            ..self
+        }
+    }
+
+    /// Enables TLS with the specified configuration.
+    ///
+    /// The protocols enabled by `http1` and `http2` are advertised via ALPN. The
+    /// information about the negotiated session is available through `Context::connection_info`.
+    ///
+    /// This method is available only if the feature `tls` is enabled.
+    #[cfg(feature = "tls")]
+    pub fn tls(self, config: TlsConfig) -> Self {
+        Server {
+            tls: Some(config),
+            ..self
+        }
+    }
+
+    /// Returns a handle for observing the number of requests currently being handled by the server.
+    pub fn in_flight_requests(&self) -> InFlightRequests {
+        InFlightRequests(self.in_flight.clone())
+    }
+
+    fn protocol(&self) -> ServerResult<Http> {
+        let mut protocol = Http::new();
+        protocol.keep_alive(self.keep_alive);
+        match (self.http1, self.http2) {
+            (true, true) => {}
+            (true, false) => {
+                protocol.http1_only(true);
+            }
+            (false, true) => {
+                protocol.http2_only(true);
+            }
+            (false, false) => {
+                return Err(ServerError::config("both HTTP/1 and HTTP/2 are disabled"));
+            }
+        }
+        Ok(protocol)
     }
 }
 
-impl<S, Bd> ServiceBuilder<S>
+impl<S, Bd> Server<S>
 where
-    S: NewService<Request = Request<Body>, Response = Response<Bd>> + Send + Sync + 'static,
+    S: MakeService<ConnectionInfo, Request<RequestBody>, Response = Response<Bd>>
+        + Send
+        + Sync
+        + 'static,
     S::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
-    S::InitError: Into<Box<dyn error::Error + Send + Sync + 'static>>,
-    S::Service: Send,
+    S::MakeError: Into<Box<dyn error::Error + Send + Sync + 'static>>,
     S::Future: Send + 'static,
-    <S::Service as tower_service::Service>::Future: Send + 'static,
-    Bd: Payload,
+    S::Service: Send + 'static,
+    <S::Service as Service<Request<RequestBody>>>::Future: Send + 'static,
+    Bd: BufStream + Send + 'static,
+    Bd::Item: Send,
+    Bd::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
 {
-    /// Start the server with the specified configuration.
-    pub fn serve(self, config: impl ServerConfig) -> ServerResult<()> {
-        self.serve_with_graceful_shutdown(config, future::empty::<(), ()>())
+    /// Binds a TCP listener to the specified address and starts the server.
+    ///
+    /// This method blocks the current thread while the server is running.
+    pub fn serve(self, addr: impl ToSocketAddrs) -> ServerResult<()> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        self.serve_listener(listener)
+    }
+
+    /// Starts the server on the specified TCP listener which has already been bound.
+    ///
+    /// This method blocks the current thread while the server is running.
+    pub fn serve_listener(self, listener: std::net::TcpListener) -> ServerResult<()> {
+        self.serve_incoming(move || TcpIncoming::from_std(listener))
+    }
+
+    /// Binds a Unix domain socket with the specified configuration and starts the server.
+    ///
+    /// The peer credentials of each connection are available through `Context::connection_info`.
+    ///
+    /// This method blocks the current thread while the server is running.
+    #[cfg(unix)]
+    pub fn serve_unix(self, socket: impl Into<UnixSocket>) -> ServerResult<()> {
+        let listener = socket.into().bind()?;
+        self.serve_incoming(move || UnixIncoming::from_std(listener))
     }
 
-    /// Start the server with the specified configuration and shutdown signal.
-    pub fn serve_with_graceful_shutdown(
+    #[cfg_attr(not(feature = "tls"), allow(unused_mut))]
+    fn serve_incoming<L>(
+        mut self,
+        make_incoming: impl FnOnce() -> io::Result<L> + Send + 'static,
+    ) -> ServerResult<()>
+    where
+        L: Listener + Send + 'static,
+    {
+        let protocol = self.protocol()?;
+
+        #[cfg(feature = "tls")]
+        {
+            if let Some(tls) = self.tls.take() {
+                let mut alpn_protocols: Vec<&[u8]> = vec![];
+                if self.http2 {
+                    alpn_protocols.push(b"h2");
+                }
+                if self.http1 {
+                    alpn_protocols.push(b"http/1.1");
+                }
+                let acceptor = tls.build(&alpn_protocols).map_err(ServerError::config)?;
+                return self.run_incoming(protocol, move || {
+                    make_incoming().map(|incoming| TlsIncoming::new(incoming, acceptor))
+                });
+            }
+        }
+
+        self.run_incoming(protocol, make_incoming)
+    }
+
+    fn run_incoming<L>(
         self,
-        server_config: impl ServerConfig,
-        signal: impl Future<Item = ()> + Send + 'static,
-    ) -> ServerResult<()> {
-        server_config
-            .build()
-            .map_err(ServerError::config)?
-            .serve_with_graceful_shutdown(self, signal);
-        Ok(())
+        protocol: Http,
+        make_incoming: impl FnOnce() -> io::Result<L> + Send + 'static,
+    ) -> ServerResult<()>
+    where
+        L: Listener + Send + 'static,
+    {
+        let make_service = self.make_service;
+        let graceful = Graceful {
+            signal: self.shutdown_signal,
+            timeout: self.shutdown_timeout,
+            in_flight: self.in_flight,
+        };
+        run(
+            self.runtime,
+            future::lazy(move || {
+                make_incoming()
+                    .map(|incoming| Serve::new(incoming, make_service, protocol, graceful))
+            })
+            .flatten(),
+        )
+    }
+}
+
+fn run<F>(runtime: RuntimeMode, future: F) -> ServerResult<()>
+where
+    F: Future<Item = (), Error = io::Error> + Send + 'static,
+{
+    match runtime {
+        RuntimeMode::ThreadPool => {
+            let mut rt = tokio::runtime::Runtime::new()?;
+            let result = rt.block_on(future);
+            let _ = rt.shutdown_now().wait();
+            result.map_err(Into::into)
+        }
+        RuntimeMode::CurrentThread => {
+            let mut rt = tokio::runtime::current_thread::Runtime::new()?;
+            rt.block_on(future).map_err(Into::into)
+        }
     }
 }
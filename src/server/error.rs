@@ -1,31 +1,36 @@
-use failure::Error;
-use std::error;
-use std::fmt;
+use std::{error, fmt, io};
 
 /// A type alias of `Result<T, E>` whose error type is restrected to `ServerError`.
 pub type ServerResult<T> = Result<T, ServerError>;
 
 #[derive(Debug)]
 enum ServerErrorKind {
-    Config(Error),
-    Custom(Error),
+    Config(Box<dyn error::Error + Send + Sync + 'static>),
+    Io(io::Error),
+    Custom(Box<dyn error::Error + Send + Sync + 'static>),
 }
 
-/// The error type which will be returned from `ServiceBuilder::serve()`.
+/// The error type which will be returned from `Server::serve()`.
 #[derive(Debug)]
 pub struct ServerError {
     kind: ServerErrorKind,
 }
 
 impl ServerError {
-    pub(super) fn config(err: impl Into<Error>) -> ServerError {
+    pub(super) fn config(err: impl Into<Box<dyn error::Error + Send + Sync + 'static>>) -> Self {
         ServerError {
             kind: ServerErrorKind::Config(err.into()),
         }
     }
 
+    pub(super) fn io(err: io::Error) -> Self {
+        ServerError {
+            kind: ServerErrorKind::Io(err),
+        }
+    }
+
     /// Create a value of `ServerError` from an arbitrary error value.
-    pub fn custom(err: impl Into<Error>) -> ServerError {
+    pub fn custom(err: impl Into<Box<dyn error::Error + Send + Sync + 'static>>) -> Self {
         ServerError {
             kind: ServerErrorKind::Custom(err.into()),
         }
@@ -36,14 +41,25 @@ impl fmt::Display for ServerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use self::ServerErrorKind::*;
         match self.kind {
-            Config(ref e) => write!(f, "failed to build server config: {}", e),
+            Config(ref e) => write!(f, "invalid server configuration: {}", e),
+            Io(ref e) => write!(f, "I/O error in the server: {}", e),
             Custom(ref e) => fmt::Display::fmt(e, f),
         }
     }
 }
 
 impl error::Error for ServerError {
-    fn description(&self) -> &str {
-        "failed to start the server"
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        use self::ServerErrorKind::*;
+        match self.kind {
+            Config(ref e) | Custom(ref e) => Some(&**e),
+            Io(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ServerError {
+    fn from(err: io::Error) -> Self {
+        ServerError::io(err)
     }
 }
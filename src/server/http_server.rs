@@ -1,180 +1,357 @@
-use std::error;
-use std::net::{IpAddr, SocketAddr};
-use std::sync::Arc;
-
-use failure::Fallible;
-use futures::future;
-use futures::{Future, Stream};
-use http::{Request, Response};
-use hyper;
-use hyper::body::Payload;
-use hyper::server::conn::AddrIncoming;
-use hyper::Body;
-use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::runtime::Runtime;
-use tower_service::{NewService, Service};
-
-use crate::rt::{with_set_runtime_mode, RuntimeMode};
-
-/// A trait representing the configuration to start the HTTP server.
-pub trait ServerConfig: ServerConfigImpl {}
-
-impl<'a> ServerConfig for &'a str {}
-impl ServerConfig for String {}
-impl<I: Into<IpAddr>> ServerConfig for (I, u16) {}
-impl ServerConfig for SocketAddr {}
-impl<'a> ServerConfig for &'a SocketAddr {}
-
-pub trait ServerConfigImpl {
-    type Item: AsyncRead + AsyncWrite + Send + 'static;
-    type Error: Into<Box<dyn error::Error + Send + Sync + 'static>>;
-    type Incoming: Stream<Item = Self::Item, Error = Self::Error> + Send + 'static;
-
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>>;
-}
+use {
+    super::{
+        listener::Listener,
+        shutdown::{Counter, Guard, ShutdownSignal},
+    },
+    crate::service::ConnectionInfo,
+    futures::{
+        future::{self, Shared},
+        sync::oneshot,
+        Async, Future, Poll,
+    },
+    http::{Request, Response},
+    hyper::{
+        body::{Body, Chunk, Payload},
+        server::conn::Http,
+        upgrade::{OnUpgrade, Upgraded},
+    },
+    izanami_service::{MakeService, Service},
+    izanami_util::{
+        buf_stream::{BufStream, SizeHint},
+        http::Upgrade,
+    },
+    std::{
+        error, io,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    tokio::timer::Delay,
+};
+
+type BoxedStdError = Box<dyn error::Error + Send + Sync + 'static>;
+
+// ==== RequestBody ====
 
-impl<'a> ServerConfigImpl for &'a str {
-    type Item = <AddrIncoming as Stream>::Item;
-    type Error = <AddrIncoming as Stream>::Error;
-    type Incoming = AddrIncoming;
+/// The type of request body passed to the services run by the server.
+#[derive(Debug)]
+pub struct RequestBody(Body);
+
+impl BufStream for RequestBody {
+    type Item = Chunk;
+    type Error = hyper::Error;
 
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>> {
-        ServerConfigImpl::build(&self.parse::<SocketAddr>()?)
+    fn poll_buf(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.0.poll_data()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let mut hint = SizeHint::new();
+        if let Some(len) = self.0.content_length() {
+            hint.set_upper(len);
+            hint.set_lower(len);
+        }
+        hint
     }
 }
 
-impl ServerConfigImpl for String {
-    type Item = <AddrIncoming as Stream>::Item;
-    type Error = <AddrIncoming as Stream>::Error;
-    type Incoming = AddrIncoming;
+impl Upgrade for RequestBody {
+    type Upgraded = Upgraded;
+    type Error = hyper::Error;
+    type Future = OnUpgrade;
 
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>> {
-        ServerConfigImpl::build(self.as_str())
+    fn on_upgrade(self) -> Self::Future {
+        self.0.on_upgrade()
     }
 }
 
-impl<I: Into<IpAddr>> ServerConfigImpl for (I, u16) {
-    type Item = <AddrIncoming as Stream>::Item;
-    type Error = <AddrIncoming as Stream>::Error;
-    type Incoming = AddrIncoming;
+// ==== ResponseBody ====
+
+#[derive(Debug)]
+pub(crate) struct ResponseBody<Bd>(Bd);
 
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>> {
-        ServerConfigImpl::build(SocketAddr::from(self))
+impl<Bd> Payload for ResponseBody<Bd>
+where
+    Bd: BufStream + Send + 'static,
+    Bd::Item: Send,
+    Bd::Error: Into<BoxedStdError>,
+{
+    type Data = Bd::Item;
+    type Error = Bd::Error;
+
+    fn poll_data(&mut self) -> Poll<Option<Self::Data>, Self::Error> {
+        self.0.poll_buf()
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        let hint = self.0.size_hint();
+        match hint.upper() {
+            Some(upper) if upper == hint.lower() => Some(upper),
+            _ => None,
+        }
     }
 }
 
-impl ServerConfigImpl for SocketAddr {
-    type Item = <AddrIncoming as Stream>::Item;
-    type Error = <AddrIncoming as Stream>::Error;
-    type Incoming = AddrIncoming;
+// ==== HttpService ====
 
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>> {
-        ServerConfigImpl::build(&self)
-    }
+/// An adapter for driving an HTTP service on the connections handled by Hyper.
+#[derive(Debug)]
+struct HttpService<S> {
+    service: S,
+    info: ConnectionInfo,
+    in_flight: Arc<Counter>,
 }
 
-impl<'a> ServerConfigImpl for &'a SocketAddr {
-    type Item = <AddrIncoming as Stream>::Item;
-    type Error = <AddrIncoming as Stream>::Error;
-    type Incoming = AddrIncoming;
+impl<S, Bd> hyper::service::Service for HttpService<S>
+where
+    S: Service<Request<RequestBody>, Response = Response<Bd>>,
+    S::Error: Into<BoxedStdError>,
+    Bd: BufStream + Send + 'static,
+    Bd::Item: Send,
+    Bd::Error: Into<BoxedStdError>,
+{
+    type ReqBody = Body;
+    type ResBody = ResponseBody<Bd>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
 
-    fn build(self) -> Fallible<HttpServer<Self::Incoming>> {
-        let builder = hyper::server::Server::try_bind(self)?;
-        let rt = Runtime::new()?;
-        Ok(HttpServer { builder, rt })
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let mut request = request.map(RequestBody);
+        request.extensions_mut().insert(self.info.clone());
+        ResponseFuture {
+            future: self.service.call(request),
+            _guard: self.in_flight.guard(),
+        }
     }
 }
 
+/// A future which tracks a request being handled by the server until the response is created.
 #[derive(Debug)]
-pub struct HttpServer<I> {
-    builder: hyper::server::Builder<I>,
-    rt: Runtime,
+struct ResponseFuture<F> {
+    future: F,
+    _guard: Guard,
 }
 
-impl<I> HttpServer<I>
+impl<F, Bd> Future for ResponseFuture<F>
 where
-    I: Stream + Send + 'static,
-    I::Item: AsyncRead + AsyncWrite + Send + 'static,
-    I::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
+    F: Future<Item = Response<Bd>>,
 {
-    pub(super) fn serve_with_graceful_shutdown<S, Bd>(
-        self,
-        new_service: S,
-        signal: impl Future<Item = ()> + Send + 'static,
-    ) where
-        S: NewService<Request = Request<Body>, Response = Response<Bd>> + Send + Sync + 'static,
-        S::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
-        S::InitError: Into<Box<dyn error::Error + Send + Sync + 'static>>,
-        S::Service: Send,
-        S::Future: Send + 'static,
-        <S::Service as Service>::Future: Send + 'static,
-        Bd: Payload,
-    {
-        let Self { builder, mut rt } = self;
-
-        // Put the instance of new_service into the heap and ensure that
-        // it lives until enter the scope.
-        //
-        // It implies that all tasks spawned by Tokio runtime must be dropped
-        // after executing `shutdown_on_idle()`.
-        let new_service = Arc::new(new_service);
-
-        let mut server = builder
-            .serve(NewHttpService(new_service.clone()))
-            .with_graceful_shutdown(signal)
-            .map_err(|err| log::error!("server error: {}", err));
-
-        let server = future::poll_fn(move || {
-            with_set_runtime_mode(RuntimeMode::ThreadPool, || server.poll())
-        });
-
-        rt.spawn(server);
-        rt.shutdown_on_idle().wait().unwrap();
+    type Item = Response<ResponseBody<Bd>>;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.future
+            .poll()
+            .map(|x| x.map(|response| response.map(ResponseBody)))
     }
 }
 
+// ==== Serve ====
+
+/// The configuration of graceful shutdown.
 #[derive(Debug)]
-struct NewHttpService<S>(S);
+pub(crate) struct Graceful {
+    pub(crate) signal: Option<ShutdownSignal>,
+    pub(crate) timeout: Duration,
+    pub(crate) in_flight: Arc<Counter>,
+}
 
-impl<S, ReqBody, ResBody> hyper::service::NewService for NewHttpService<S>
+/// A future which accepts the incoming connections and spawns a task for each connection.
+///
+/// When the shutdown signal is fired, this future stops accepting new connections
+/// and waits for the completion of the connections in progress.
+#[allow(missing_debug_implementations)]
+pub(crate) struct Serve<L, S> {
+    listener: Option<L>,
+    make_service: Arc<S>,
+    protocol: Http,
+    backoff: Option<Delay>,
+    graceful: Graceful,
+    connections: Arc<Counter>,
+    notify: Option<oneshot::Sender<()>>,
+    watch: Shared<oneshot::Receiver<()>>,
+    deadline: Option<Delay>,
+}
+
+impl<L, S, Bd> Serve<L, S>
 where
-    S: NewService<Request = Request<ReqBody>, Response = Response<ResBody>>,
-    ReqBody: Payload,
-    ResBody: Payload,
-    S::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
-    S::InitError: Into<Box<dyn error::Error + Send + Sync + 'static>>,
+    L: Listener,
+    S: MakeService<ConnectionInfo, Request<RequestBody>, Response = Response<Bd>>
+        + Send
+        + Sync
+        + 'static,
+    S::Error: Into<BoxedStdError>,
+    S::MakeError: Into<BoxedStdError>,
+    S::Future: Send + 'static,
+    S::Service: Send + 'static,
+    <S::Service as Service<Request<RequestBody>>>::Future: Send + 'static,
+    Bd: BufStream + Send + 'static,
+    Bd::Item: Send,
+    Bd::Error: Into<BoxedStdError>,
 {
-    type ReqBody = ReqBody;
-    type ResBody = ResBody;
-    type Error = S::Error;
-    type Service = HttpService<S::Service>;
-    type InitError = S::InitError;
-    #[allow(clippy::type_complexity)]
-    type Future = future::Map<S::Future, fn(S::Service) -> HttpService<S::Service>>;
-
-    #[inline]
-    fn new_service(&self) -> Self::Future {
-        self.0.new_service().map(HttpService)
+    pub(crate) fn new(listener: L, make_service: S, protocol: Http, graceful: Graceful) -> Self {
+        let (notify, watch) = oneshot::channel();
+        Serve {
+            listener: Some(listener),
+            make_service: Arc::new(make_service),
+            protocol,
+            backoff: None,
+            graceful,
+            connections: Arc::new(Counter::default()),
+            notify: Some(notify),
+            watch: watch.shared(),
+            deadline: None,
+        }
     }
-}
 
-#[derive(Debug)]
-struct HttpService<S>(S);
+    fn spawn_connection(&self, accept: L::Accept) {
+        let make_service = self.make_service.clone();
+        let protocol = self.protocol.clone();
+        let in_flight = self.graceful.in_flight.clone();
+        let mut watch = Some(self.watch.clone());
+        let guard = self.connections.guard();
+        let future = accept
+            .map_err(|err| log::debug!("failed to establish the connection: {}", err))
+            .and_then(move |(conn, info)| {
+                make_service
+                    .make_service(info.clone())
+                    .map_err(|err| log::error!("failed to create a service: {}", err.into()))
+                    .map(move |service| (conn, info, service))
+            })
+            .and_then(move |(conn, info, service)| {
+                let service = HttpService {
+                    service,
+                    info,
+                    in_flight,
+                };
+                let mut conn = protocol.serve_connection(conn, service).with_upgrades();
+                future::poll_fn(move || {
+                    if let Some(ref mut w) = watch {
+                        match w.poll() {
+                            Ok(Async::NotReady) => {}
+                            Ok(Async::Ready(..)) | Err(..) => {
+                                conn.graceful_shutdown();
+                                watch = None;
+                            }
+                        }
+                    }
+                    conn.poll()
+                })
+                .map_err(|err| log::debug!("connection error: {}", err))
+            })
+            .then(move |result| {
+                drop(guard);
+                result
+            });
+        tokio::spawn(future);
+    }
 
-impl<S, ReqBody, ResBody> hyper::service::Service for HttpService<S>
+    fn poll_accept(&mut self) -> Poll<(), io::Error> {
+        loop {
+            if let Some(ref mut backoff) = self.backoff {
+                match backoff.poll() {
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Ok(Async::Ready(())) | Err(..) => {}
+                }
+            }
+            self.backoff = None;
+
+            let accepted = match self.listener {
+                Some(ref mut listener) => listener.poll_accept(),
+                None => return Ok(Async::Ready(())),
+            };
+            match accepted {
+                Ok(Async::Ready(accept)) => self.spawn_connection(accept),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(ref err) if is_connection_error(err) => {
+                    log::debug!("accepted connection already errored: {}", err);
+                }
+                Err(err) => {
+                    // The errors such as `EMFILE` are likely to occur again immediately,
+                    // so the server waits for a while before accepting the next connection.
+                    log::error!("accept error: {}", err);
+                    self.backoff = Some(Delay::new(Instant::now() + Duration::from_secs(1)));
+                }
+            }
+        }
+    }
+
+    fn start_shutdown(&mut self) {
+        log::info!(
+            "shutting down the server ({} requests in flight)",
+            self.graceful.in_flight.get()
+        );
+        // Dropping the listener closes the socket, so that the new connections are refused.
+        self.listener = None;
+        if let Some(notify) = self.notify.take() {
+            let _ = notify.send(());
+        }
+        self.deadline = Some(Delay::new(Instant::now() + self.graceful.timeout));
+    }
+
+    fn poll_drain(&mut self) -> Poll<(), io::Error> {
+        if self.connections.poll_zero() {
+            return Ok(Async::Ready(()));
+        }
+        let deadline = self
+            .deadline
+            .as_mut()
+            .expect("the shutdown should have been started");
+        match deadline.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(())) | Err(..) => {
+                log::warn!(
+                    "the shutdown timeout has elapsed with {} requests still in flight",
+                    self.graceful.in_flight.get()
+                );
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+}
+
+impl<L, S, Bd> Future for Serve<L, S>
 where
-    S: Service<Request = Request<ReqBody>, Response = Response<ResBody>>,
-    ReqBody: Payload,
-    ResBody: Payload,
-    S::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
+    L: Listener,
+    S: MakeService<ConnectionInfo, Request<RequestBody>, Response = Response<Bd>>
+        + Send
+        + Sync
+        + 'static,
+    S::Error: Into<BoxedStdError>,
+    S::MakeError: Into<BoxedStdError>,
+    S::Future: Send + 'static,
+    S::Service: Send + 'static,
+    <S::Service as Service<Request<RequestBody>>>::Future: Send + 'static,
+    Bd: BufStream + Send + 'static,
+    Bd::Item: Send,
+    Bd::Error: Into<BoxedStdError>,
 {
-    type ReqBody = ReqBody;
-    type ResBody = ResBody;
-    type Error = S::Error;
-    type Future = S::Future;
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.listener.is_some() {
+            let fired = match self.graceful.signal {
+                Some(ref mut signal) => signal.poll_fired(),
+                None => false,
+            };
+            if !fired {
+                return self.poll_accept();
+            }
+            self.start_shutdown();
+        }
+        self.poll_drain()
+    }
+}
 
-    #[inline]
-    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
-        self.0.call(request)
+fn is_connection_error(err: &io::Error) -> bool {
+    match err.kind() {
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset => true,
+        _ => false,
     }
 }
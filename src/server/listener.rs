@@ -0,0 +1,52 @@
+use {
+    crate::service::ConnectionInfo,
+    futures::{future::FutureResult, Async, Future, Poll},
+    std::{io, net::SocketAddr},
+    tokio::{
+        io::{AsyncRead, AsyncWrite},
+        net::{TcpListener, TcpStream},
+        reactor::Handle,
+    },
+};
+
+/// A source of the incoming connections accepted by the server.
+pub(crate) trait Listener {
+    type Conn: AsyncRead + AsyncWrite + Send + 'static;
+
+    /// The future which establishes an accepted connection, e.g. by performing the TLS handshake.
+    type Accept: Future<Item = (Self::Conn, ConnectionInfo), Error = io::Error> + Send + 'static;
+
+    fn poll_accept(&mut self) -> Poll<Self::Accept, io::Error>;
+}
+
+#[derive(Debug)]
+pub(crate) struct TcpIncoming {
+    listener: TcpListener,
+    local_addr: SocketAddr,
+}
+
+impl TcpIncoming {
+    /// Registers the listener to the reactor of the current runtime.
+    pub(crate) fn from_std(listener: std::net::TcpListener) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let listener = TcpListener::from_std(listener, &Handle::default())?;
+        Ok(TcpIncoming {
+            listener,
+            local_addr,
+        })
+    }
+}
+
+impl Listener for TcpIncoming {
+    type Conn = TcpStream;
+    type Accept = FutureResult<(Self::Conn, ConnectionInfo), io::Error>;
+
+    fn poll_accept(&mut self) -> Poll<Self::Accept, io::Error> {
+        let (stream, remote_addr) = futures::try_ready!(self.listener.poll_accept());
+        stream.set_nodelay(true)?;
+        let info = ConnectionInfo::new()
+            .with_remote_addr(remote_addr)
+            .with_local_addr(self.local_addr);
+        Ok(Async::Ready(Ok((stream, info)).into()))
+    }
+}
@@ -0,0 +1,86 @@
+use {
+    futures::{task::AtomicTask, Async, Future},
+    std::{
+        fmt,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// The future which notifies the server to start the graceful shutdown.
+pub(crate) struct ShutdownSignal(Box<dyn Future<Item = (), Error = ()> + Send + 'static>);
+
+impl fmt::Debug for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSignal").finish()
+    }
+}
+
+impl ShutdownSignal {
+    pub(crate) fn new<F>(signal: F) -> Self
+    where
+        F: Future + Send + 'static,
+    {
+        ShutdownSignal(Box::new(signal.then(|_| Ok(()))))
+    }
+
+    /// Returns `true` if the signal has been fired.
+    pub(crate) fn poll_fired(&mut self) -> bool {
+        match self.0.poll() {
+            Ok(Async::NotReady) => false,
+            Ok(Async::Ready(())) | Err(()) => true,
+        }
+    }
+}
+
+/// A counter of the running tasks, which notifies the waiter when it reaches zero.
+#[derive(Debug, Default)]
+pub(crate) struct Counter {
+    count: AtomicUsize,
+    task: AtomicTask,
+}
+
+impl Counter {
+    pub(crate) fn get(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn guard(self: &Arc<Self>) -> Guard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        Guard(self.clone())
+    }
+
+    /// Returns `true` if there are no running tasks, or registers the current task
+    /// to be notified when the count reaches zero.
+    pub(crate) fn poll_zero(&self) -> bool {
+        self.task.register();
+        self.get() == 0
+    }
+}
+
+/// A guard which decrements the counter when dropped.
+#[derive(Debug)]
+pub(crate) struct Guard(Arc<Counter>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.task.notify();
+        }
+    }
+}
+
+/// A handle for observing the number of requests currently being handled by the server.
+///
+/// The value of this type is created by `Server::in_flight_requests`.
+#[derive(Debug, Clone)]
+pub struct InFlightRequests(pub(crate) Arc<Counter>);
+
+impl InFlightRequests {
+    /// Returns the number of requests currently being handled.
+    pub fn get(&self) -> usize {
+        self.0.get()
+    }
+}
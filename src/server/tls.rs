@@ -0,0 +1,397 @@
+//! TLS support based on `rustls`.
+
+use {
+    super::listener::Listener,
+    crate::service::{ConnectionInfo, TlsInfo},
+    futures::{Async, Future, Poll},
+    rustls::{
+        internal::pemfile,
+        sign::{self, CertifiedKey, SigningKey},
+        AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, Certificate,
+        NoClientAuth, PrivateKey, ResolvesServerCert, RootCertStore, ServerConfig, ServerSession,
+        Session, SignatureScheme,
+    },
+    std::{
+        fmt, fs, io,
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock, Weak},
+        thread,
+        time::Duration,
+    },
+    tokio::timer::Timeout,
+    tokio_rustls::server::TlsStream,
+};
+
+/// The configuration of TLS used by the server.
+///
+/// # Example
+///
+/// ```no_run
+/// # use finchers::prelude::*;
+/// use finchers::server::tls::TlsConfig;
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), finchers::server::ServerError> {
+/// # let endpoint = endpoint::unit().map(|| "Hello");
+/// let tls = TlsConfig::new("/etc/myapp/cert.pem", "/etc/myapp/key.pem")
+///     .reload_interval(Duration::from_secs(60));
+///
+/// finchers::server::start(endpoint)
+///     .tls(tls)
+///     .serve("0.0.0.0:443")
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    client_auth: ClientAuth,
+    reload_interval: Option<Duration>,
+    handshake_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum ClientAuth {
+    None,
+    Optional(PathBuf),
+    Required(PathBuf),
+}
+
+impl TlsConfig {
+    /// Creates a new `TlsConfig` from the paths of PEM files.
+    ///
+    /// The certificate file contains the certificate chain, starting with the end-entity
+    /// certificate. The key file contains a private key in the PKCS#8 or PKCS#1 (RSA) format.
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            client_auth: ClientAuth::None,
+            reload_interval: None,
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Requests the clients to send a certificate signed by one of the CAs in the specified PEM file.
+    ///
+    /// The clients without certificates are still accepted.
+    pub fn client_auth_optional(self, ca_path: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            client_auth: ClientAuth::Optional(ca_path.into()),
+            ..self
+        }
+    }
+
+    /// Requires the clients to send a certificate signed by one of the CAs in the specified PEM file.
+    pub fn client_auth_required(self, ca_path: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            client_auth: ClientAuth::Required(ca_path.into()),
+            ..self
+        }
+    }
+
+    /// Enables reloading the certificate and private key when the files are changed.
+    ///
+    /// The files are checked at the specified interval by a background thread, and
+    /// the new handshakes use the reloaded certificate. If the reloaded files are
+    /// invalid or the private key does not match the certificate, the error is logged
+    /// and the previous certificate continues to be used until the next check.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `interval` is zero.
+    pub fn reload_interval(self, interval: Duration) -> Self {
+        assert!(
+            interval > Duration::from_secs(0),
+            "the reload interval must be greater than zero"
+        );
+        TlsConfig {
+            reload_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Sets the maximum duration to wait for the completion of TLS handshakes.
+    ///
+    /// The default value is 10 seconds.
+    pub fn handshake_timeout(self, timeout: Duration) -> Self {
+        TlsConfig {
+            handshake_timeout: timeout,
+            ..self
+        }
+    }
+
+    pub(crate) fn build(&self, alpn_protocols: &[&[u8]]) -> io::Result<TlsAcceptor> {
+        let verifier = match self.client_auth {
+            ClientAuth::None => NoClientAuth::new(),
+            ClientAuth::Optional(ref path) => {
+                AllowAnyAnonymousOrAuthenticatedClient::new(load_root_store(path)?)
+            }
+            ClientAuth::Required(ref path) => {
+                AllowAnyAuthenticatedClient::new(load_root_store(path)?)
+            }
+        };
+        let mut config = ServerConfig::new(verifier);
+        config.cert_resolver = Arc::new(CertResolver::new(
+            self.cert_path.clone(),
+            self.key_path.clone(),
+            self.reload_interval,
+        )?);
+        config.set_protocols(
+            &alpn_protocols
+                .iter()
+                .map(|protocol| protocol.to_vec())
+                .collect::<Vec<_>>(),
+        );
+        Ok(TlsAcceptor {
+            inner: tokio_rustls::TlsAcceptor::from(Arc::new(config)),
+            handshake_timeout: self.handshake_timeout,
+        })
+    }
+}
+
+/// The configuration built from `TlsConfig`, shared by all connections.
+#[derive(Clone)]
+pub(crate) struct TlsAcceptor {
+    inner: tokio_rustls::TlsAcceptor,
+    handshake_timeout: Duration,
+}
+
+impl fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsAcceptor")
+            .field("handshake_timeout", &self.handshake_timeout)
+            .finish()
+    }
+}
+
+fn invalid_data(msg: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_certs(pem: &[u8], path: &Path) -> io::Result<Vec<Certificate>> {
+    let certs = pemfile::certs(&mut &pem[..])
+        .map_err(|()| invalid_data(format!("invalid certificate file: {}", path.display())))?;
+    if certs.is_empty() {
+        return Err(invalid_data(format!(
+            "no certificates found in {}",
+            path.display()
+        )));
+    }
+    Ok(certs)
+}
+
+fn parse_private_key(pem: &[u8], path: &Path) -> io::Result<PrivateKey> {
+    let invalid = || invalid_data(format!("invalid private key file: {}", path.display()));
+    let mut keys = pemfile::pkcs8_private_keys(&mut &pem[..]).map_err(|()| invalid())?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut &pem[..]).map_err(|()| invalid())?;
+    }
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| invalid_data(format!("no private keys found in {}", path.display())))
+}
+
+fn load_root_store(path: &Path) -> io::Result<RootCertStore> {
+    let mut store = RootCertStore::empty();
+    for cert in parse_certs(&fs::read(path)?, path)? {
+        store
+            .add(&cert)
+            .map_err(|err| invalid_data(format!("invalid CA certificate: {:?}", err)))?;
+    }
+    Ok(store)
+}
+
+// ==== CertResolver ====
+
+/// A resolver of the server certificate.
+///
+/// The certificate is replaced by the background thread spawned in `CertResolver::new`
+/// when the reloading is enabled, so the handshakes never touch the files.
+struct CertResolver {
+    key: Arc<RwLock<CertifiedKey>>,
+}
+
+impl fmt::Debug for CertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertResolver").finish()
+    }
+}
+
+impl CertResolver {
+    fn new(
+        cert_path: PathBuf,
+        key_path: PathBuf,
+        reload_interval: Option<Duration>,
+    ) -> io::Result<Self> {
+        let contents = (fs::read(&cert_path)?, fs::read(&key_path)?);
+        let key = Arc::new(RwLock::new(parse_certified_key(
+            &contents, &cert_path, &key_path,
+        )?));
+        if let Some(interval) = reload_interval {
+            let reloader = Reloader {
+                cert_path,
+                key_path,
+                interval,
+                contents,
+                key: Arc::downgrade(&key),
+            };
+            thread::Builder::new()
+                .name("finchers-tls-reload".into())
+                .spawn(move || reloader.run())?;
+        }
+        Ok(CertResolver { key })
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(
+        &self,
+        _: Option<webpki::DNSNameRef<'_>>,
+        _: &[SignatureScheme],
+    ) -> Option<CertifiedKey> {
+        let key = self.key.read().unwrap_or_else(|e| e.into_inner());
+        Some(key.clone())
+    }
+}
+
+/// The task run in the background thread which reloads the certificate when the files are changed.
+///
+/// The thread exits after the resolver has been dropped.
+struct Reloader {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    interval: Duration,
+    contents: (Vec<u8>, Vec<u8>),
+    key: Weak<RwLock<CertifiedKey>>,
+}
+
+impl Reloader {
+    fn run(mut self) {
+        loop {
+            thread::sleep(self.interval);
+            let key = match self.key.upgrade() {
+                Some(key) => key,
+                None => return,
+            };
+            match self.reload_if_changed() {
+                Ok(Some(reloaded)) => {
+                    *key.write().unwrap_or_else(|e| e.into_inner()) = reloaded;
+                    log::info!("reloaded the certificate: {}", self.cert_path.display());
+                }
+                Ok(None) => {}
+                Err(err) => log::warn!("failed to reload the certificate: {}", err),
+            }
+        }
+    }
+
+    fn reload_if_changed(&mut self) -> io::Result<Option<CertifiedKey>> {
+        let contents = (fs::read(&self.cert_path)?, fs::read(&self.key_path)?);
+        if contents == self.contents {
+            return Ok(None);
+        }
+        // The contents are updated only on success, so that the files are read again
+        // at the next interval if they were in the middle of being replaced.
+        let key = parse_certified_key(&contents, &self.cert_path, &self.key_path)?;
+        self.contents = contents;
+        Ok(Some(key))
+    }
+}
+
+fn parse_certified_key(
+    (cert_pem, key_pem): &(Vec<u8>, Vec<u8>),
+    cert_path: &Path,
+    key_path: &Path,
+) -> io::Result<CertifiedKey> {
+    let certs = parse_certs(cert_pem, cert_path)?;
+    let key = sign::any_supported_type(&parse_private_key(key_pem, key_path)?)
+        .map_err(|()| invalid_data("unsupported type of private key"))?;
+    verify_key_matches_cert(&*key, &certs[0]).map_err(|()| {
+        invalid_data(format!(
+            "the private key in {} does not match the certificate in {}",
+            key_path.display(),
+            cert_path.display()
+        ))
+    })?;
+    Ok(CertifiedKey::new(certs, Arc::new(key)))
+}
+
+/// Checks that the private key belongs to the certificate, by signing a probe message
+/// and verifying the signature with the public key in the certificate.
+fn verify_key_matches_cert(key: &dyn SigningKey, cert: &Certificate) -> Result<(), ()> {
+    const PROBE: &[u8] = b"finchers-tls-key-probe";
+    let signer = key
+        .choose_scheme(&[
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ED25519,
+            SignatureScheme::RSA_PKCS1_SHA256,
+        ])
+        .ok_or(())?;
+    let algorithm = match signer.get_scheme() {
+        SignatureScheme::ECDSA_NISTP256_SHA256 => &webpki::ECDSA_P256_SHA256,
+        SignatureScheme::ECDSA_NISTP384_SHA384 => &webpki::ECDSA_P384_SHA384,
+        SignatureScheme::ED25519 => &webpki::ED25519,
+        SignatureScheme::RSA_PKCS1_SHA256 => &webpki::RSA_PKCS1_2048_8192_SHA256,
+        _ => return Err(()),
+    };
+    let signature = signer.sign(PROBE).map_err(|_| ())?;
+    webpki::EndEntityCert::from(&cert.0)
+        .and_then(|cert| cert.verify_signature(algorithm, PROBE, &signature))
+        .map_err(|_| ())
+}
+
+// ==== TlsIncoming ====
+
+/// A listener which performs the TLS handshake on the accepted connections.
+#[derive(Debug)]
+pub(crate) struct TlsIncoming<L> {
+    inner: L,
+    acceptor: TlsAcceptor,
+}
+
+impl<L> TlsIncoming<L> {
+    pub(crate) fn new(inner: L, acceptor: TlsAcceptor) -> Self {
+        TlsIncoming { inner, acceptor }
+    }
+}
+
+impl<L> Listener for TlsIncoming<L>
+where
+    L: Listener,
+{
+    type Conn = TlsStream<L::Conn>;
+    type Accept = Box<dyn Future<Item = (Self::Conn, ConnectionInfo), Error = io::Error> + Send>;
+
+    fn poll_accept(&mut self) -> Poll<Self::Accept, io::Error> {
+        let accept = futures::try_ready!(self.inner.poll_accept());
+        let acceptor = self.acceptor.inner.clone();
+        let handshake = accept.and_then(move |(io, info)| {
+            acceptor.accept(io).map(move |stream| {
+                let info = info.with_tls(tls_info(stream.get_ref().1));
+                (stream, info)
+            })
+        });
+        Ok(Async::Ready(Box::new(
+            Timeout::new(handshake, self.acceptor.handshake_timeout).map_err(|err| {
+                err.into_inner().unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::TimedOut, "the TLS handshake has timed out")
+                })
+            }),
+        )))
+    }
+}
+
+fn tls_info(session: &ServerSession) -> TlsInfo {
+    let mut info = TlsInfo::new();
+    if let Some(server_name) = session.get_sni_hostname() {
+        info = info.with_server_name(server_name);
+    }
+    if let Some(protocol) = session.get_alpn_protocol() {
+        info = info.with_alpn_protocol(protocol);
+    }
+    if let Some(certs) = session.get_peer_certificates() {
+        info = info.with_peer_certificates(certs.into_iter().map(|cert| cert.0).collect());
+    }
+    info
+}
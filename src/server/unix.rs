@@ -0,0 +1,188 @@
+use {
+    super::listener::Listener,
+    crate::service::{ConnectionInfo, PeerCredentials},
+    futures::{future::FutureResult, Async, Poll},
+    std::{
+        ffi::OsString,
+        fs, io,
+        os::unix::{
+            fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+            net,
+        },
+        path::{Path, PathBuf},
+        process,
+    },
+    tokio::reactor::Handle,
+    tokio_uds::{UnixListener, UnixStream},
+};
+
+/// The configuration of a Unix domain socket which the server listens on.
+///
+/// # Example
+///
+/// ```no_run
+/// # use finchers::prelude::*;
+/// use finchers::server::UnixSocket;
+///
+/// # fn main() -> Result<(), finchers::server::ServerError> {
+/// # let endpoint = endpoint::unit().map(|| "Hello");
+/// finchers::server::start(endpoint).serve_unix(
+///     UnixSocket::new("/run/myapp/http.sock")
+///         .permissions(0o660)
+///         .remove_stale(true),
+/// )
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnixSocket {
+    path: PathBuf,
+    permissions: Option<u32>,
+    remove_stale: bool,
+}
+
+impl UnixSocket {
+    /// Creates a new `UnixSocket` with the specified path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        UnixSocket {
+            path: path.into(),
+            permissions: None,
+            remove_stale: false,
+        }
+    }
+
+    /// Sets the permission bits of the socket file, e.g. `0o660`.
+    ///
+    /// The socket file appears at the path only after the permission has been applied.
+    /// If not specified, the permission is determined by the umask of the process.
+    pub fn permissions(self, mode: u32) -> Self {
+        UnixSocket {
+            permissions: Some(mode),
+            ..self
+        }
+    }
+
+    /// Sets whether to remove the socket file left by a previous process before binding.
+    ///
+    /// The existing file is removed only if it is a socket and no process is
+    /// listening on it. Otherwise, binding the socket fails as usual.
+    ///
+    /// The default value is `false`.
+    pub fn remove_stale(self, enabled: bool) -> Self {
+        UnixSocket {
+            remove_stale: enabled,
+            ..self
+        }
+    }
+
+    /// Returns the path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn bind(&self) -> io::Result<net::UnixListener> {
+        if self.remove_stale {
+            remove_stale_socket(&self.path)?;
+        }
+        match self.permissions {
+            Some(mode) => bind_with_permissions(&self.path, mode),
+            None => net::UnixListener::bind(&self.path),
+        }
+    }
+}
+
+impl From<PathBuf> for UnixSocket {
+    fn from(path: PathBuf) -> Self {
+        UnixSocket::new(path)
+    }
+}
+
+impl<'a> From<&'a Path> for UnixSocket {
+    fn from(path: &'a Path) -> Self {
+        UnixSocket::new(path)
+    }
+}
+
+impl From<String> for UnixSocket {
+    fn from(path: String) -> Self {
+        UnixSocket::new(path)
+    }
+}
+
+impl<'a> From<&'a str> for UnixSocket {
+    fn from(path: &'a str) -> Self {
+        UnixSocket::new(path)
+    }
+}
+
+/// Binds a socket whose file never becomes accessible with the permission derived from the umask.
+///
+/// The socket is bound in a private directory next to the destination, and then
+/// linked to the destination after its permission has been changed. Linking
+/// fails if the destination already exists, as binding does.
+fn bind_with_permissions(path: &Path, mode: u32) -> io::Result<net::UnixListener> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid socket path"))?;
+    let mut dir_name = OsString::from(".");
+    dir_name.push(file_name);
+    dir_name.push(format!(".{}.tmp", process::id()));
+    let dir = path.with_file_name(dir_name);
+
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let tmp_path = dir.join("sock");
+    let result = net::UnixListener::bind(&tmp_path).and_then(|listener| {
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(mode))?;
+        fs::hard_link(&tmp_path, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&tmp_path);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+    match net::UnixStream::connect(path) {
+        Ok(..) => Ok(()),
+        Err(ref err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+            log::debug!("removing the stale socket file: {}", path.display());
+            fs::remove_file(path)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct UnixIncoming {
+    listener: UnixListener,
+}
+
+impl UnixIncoming {
+    /// Registers the listener to the reactor of the current runtime.
+    pub(crate) fn from_std(listener: net::UnixListener) -> io::Result<Self> {
+        let listener = UnixListener::from_std(listener, &Handle::default())?;
+        Ok(UnixIncoming { listener })
+    }
+}
+
+impl Listener for UnixIncoming {
+    type Conn = UnixStream;
+    type Accept = FutureResult<(Self::Conn, ConnectionInfo), io::Error>;
+
+    fn poll_accept(&mut self) -> Poll<Self::Accept, io::Error> {
+        let (stream, _) = futures::try_ready!(self.listener.poll_accept());
+        let mut info = ConnectionInfo::new();
+        match stream.peer_cred() {
+            Ok(cred) => info = info.with_peer_credentials(PeerCredentials::new(cred.uid, cred.gid)),
+            Err(err) => log::debug!("failed to get the peer credentials: {}", err),
+        }
+        Ok(Async::Ready(Ok((stream, info)).into()))
+    }
+}
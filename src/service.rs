@@ -2,7 +2,26 @@
 
 #![allow(missing_docs)]
 
+pub mod access_log;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod cors;
+#[cfg(feature = "compression")]
+pub mod decompression;
+pub mod load_shed;
+pub mod metrics;
+pub mod request_id;
+#[cfg(feature = "tower")]
+pub mod tower;
+
 use {
+    self::{
+        access_log::AccessLog,
+        cors::Cors,
+        load_shed::{LoadShed, Permit},
+        metrics::{InFlight, Metrics},
+        request_id::{RequestId, RequestIdConfig},
+    },
     crate::{
         action::{ActionContext, EndpointAction, Preflight, PreflightContext},
         endpoint::{Endpoint, IsEndpoint},
@@ -17,7 +36,24 @@ use {
         Request, Response,
     },
     izanami_service::{MakeService, Service},
-    std::{cell::Cell, io, marker::PhantomData, ptr::NonNull, sync::Arc},
+    izanami_util::buf_stream::BufStream,
+    std::{
+        any::Any,
+        cell::Cell,
+        io,
+        marker::PhantomData,
+        net::SocketAddr,
+        panic::{self, AssertUnwindSafe},
+        ptr::NonNull,
+        sync::Arc,
+        time::{Instant, SystemTime},
+    },
+};
+
+#[cfg(feature = "compression")]
+use self::{
+    compression::{Compression, Encoded},
+    decompression::Decompression,
 };
 
 macro_rules! ready {
@@ -44,6 +80,7 @@ impl<E: IsEndpoint> EndpointServiceExt for E {
 #[derive(Debug)]
 pub struct App<E> {
     endpoint: Arc<E>,
+    config: Arc<Config>,
 }
 
 impl<E> App<E> {
@@ -51,6 +88,145 @@ impl<E> App<E> {
     pub fn new(endpoint: E) -> Self {
         App {
             endpoint: Arc::new(endpoint),
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    /// Sets whether to catch the panics occurred while handling requests.
+    ///
+    /// If enabled, a panic inside of the endpoint is logged together with
+    /// the request information and converted into a `500 Internal Server Error`
+    /// response, instead of tearing down the connection task.
+    ///
+    /// The default value is `false`.
+    pub fn catch_unwind(mut self, enabled: bool) -> Self {
+        self.config_mut().catch_unwind = enabled;
+        self
+    }
+
+    /// Enables the access logging with the specified configuration.
+    ///
+    /// The log entry is emitted through the `log` crate when the response
+    /// for each request is created.
+    pub fn access_log(mut self, access_log: AccessLog) -> Self {
+        self.config_mut().access_log = Some(access_log);
+        self
+    }
+
+    /// Enables the assignment of request IDs with the specified configuration.
+    ///
+    /// The ID of each request is accessible through `Context::request_id` or
+    /// the endpoints in `endpoints::request_id`, and is sent back to the client
+    /// in the response header. It is also included in the body of error responses
+    /// and in the JSON entries of the access log.
+    pub fn request_id(mut self, request_id: RequestIdConfig) -> Self {
+        self.config_mut().request_id = Some(request_id);
+        self
+    }
+
+    /// Enables the collection of metrics into the specified registry.
+    ///
+    /// The collected metrics can be exposed by the endpoint `endpoints::metrics::render`.
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.config_mut().metrics = Some(metrics);
+        self
+    }
+
+    /// Enables the load shedding with the specified configuration.
+    ///
+    /// The requests exceeding the limits are rejected immediately with
+    /// `503 Service Unavailable`, instead of waiting for the preceding requests.
+    pub fn load_shed(mut self, load_shed: LoadShed) -> Self {
+        self.config_mut().load_shed = Some(load_shed);
+        self
+    }
+
+    /// Enables the CORS policy for the whole application.
+    ///
+    /// The preflight requests are answered without calling the endpoint, and the
+    /// `Access-Control-*` headers are added to the responses of the actual requests.
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.config_mut().cors = Some(cors);
+        self
+    }
+
+    /// Enables the compression of the response bodies with the specified configuration.
+    ///
+    /// The encoding is negotiated from `Accept-Encoding` of each request.
+    /// See the documentation of `service::compression` for details.
+    ///
+    /// This method is available only if the feature `compression` is enabled.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config_mut().compression = Some(compression);
+        self
+    }
+
+    /// Enables the decompression of the request bodies with the specified configuration.
+    ///
+    /// The request bodies received by the endpoints in `endpoints::body` are decoded
+    /// according to `Content-Encoding`. See the documentation of `service::decompression`
+    /// for details.
+    ///
+    /// This method is available only if the feature `compression` is enabled.
+    #[cfg(feature = "compression")]
+    pub fn decompression(mut self, decompression: Decompression) -> Self {
+        self.config_mut().decompression = Some(decompression);
+        self
+    }
+
+    /// Sets the default maximum length of the request bodies received by the endpoints
+    /// in `endpoints::body`.
+    ///
+    /// The requests exceeding the limit are rejected with `413 Payload Too Large`.
+    /// The limit can be overridden for each endpoint, e.g. `body::json::<T>().limit(1 << 20)`.
+    /// Passing `None` removes the default limit, so that only the limits of the
+    /// endpoints are applied.
+    ///
+    /// The default value is 2 MiB.
+    pub fn body_limit(mut self, limit: impl Into<Option<u64>>) -> Self {
+        self.config_mut().body_limit = limit.into();
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        Arc::get_mut(&mut self.config).expect("the configuration has already been shared")
+    }
+}
+
+/// The default maximum length of the request bodies, in bytes.
+const DEFAULT_BODY_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// The set of configuration values shared by the services created from an `App`.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub(crate) catch_unwind: bool,
+    pub(crate) access_log: Option<AccessLog>,
+    pub(crate) request_id: Option<RequestIdConfig>,
+    pub(crate) metrics: Option<Metrics>,
+    pub(crate) load_shed: Option<LoadShed>,
+    pub(crate) cors: Option<Cors>,
+    #[cfg(feature = "compression")]
+    pub(crate) compression: Option<Compression>,
+    #[cfg(feature = "compression")]
+    pub(crate) decompression: Option<Decompression>,
+    pub(crate) body_limit: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            catch_unwind: false,
+            access_log: None,
+            request_id: None,
+            metrics: None,
+            load_shed: None,
+            cors: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+            #[cfg(feature = "compression")]
+            decompression: None,
+            body_limit: Some(DEFAULT_BODY_LIMIT),
         }
     }
 }
@@ -59,6 +235,7 @@ impl<E, Ctx, Bd> MakeService<Ctx, Request<Bd>> for App<E>
 where
     E: Endpoint<Bd>,
     E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
 {
     type Response = Response<ResponseBody<Bd, E>>;
     type Error = io::Error;
@@ -67,7 +244,7 @@ where
     type Future = future::FutureResult<Self::Service, Self::MakeError>;
 
     fn make_service(&self, _: Ctx) -> Self::Future {
-        future::ok(AppService::new(self.endpoint.clone()))
+        future::ok(AppService::new(self.endpoint.clone(), self.config.clone()))
     }
 }
 
@@ -75,6 +252,7 @@ where
 #[allow(missing_debug_implementations)]
 pub struct AppService<Bd, E: Endpoint<Bd>> {
     endpoint: E,
+    config: Arc<Config>,
     _marker: PhantomData<fn(Bd)>,
 }
 
@@ -82,19 +260,83 @@ impl<Bd, E> AppService<Bd, E>
 where
     E: Endpoint<Bd>,
 {
-    pub(crate) fn new(endpoint: E) -> Self {
+    pub(crate) fn new(endpoint: E, config: Arc<Config>) -> Self {
         AppService {
             endpoint,
+            config,
             _marker: PhantomData,
         }
     }
 
     pub(crate) fn dispatch(&self, request: Request<Bd>) -> AppFuture<Bd, E> {
         let (parts, body) = request.into_parts();
+        let mut context = Context::new(Request::from_parts(parts, ()));
+        #[cfg(feature = "compression")]
+        {
+            context.decompression = self.config.decompression.clone();
+        }
+        context.body_limit = self.config.body_limit;
+
+        if let Some(ref config) = self.config.request_id {
+            let request_id = config.resolve(context.headers());
+            context.response_headers().insert(
+                config.header().clone(),
+                request_id.as_header_value().clone(),
+            );
+            context.extensions_mut().insert(request_id);
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "request",
+            method = %context.method(),
+            path = %context.uri().path(),
+            request_id = tracing::field::Empty,
+            status = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(request_id) = context.request_id() {
+                span.record("request_id", request_id.as_str());
+            }
+        }
+
+        let mut cors_preflight = None;
+        if let Some(ref cors) = self.config.cors {
+            if cors::is_preflight(context.request()) {
+                cors_preflight = Some(cors.preflight(context.request()));
+            } else {
+                let headers = cors.headers(context.request());
+                context.response_headers().extend(headers);
+            }
+        }
+
+        // The preflight requests are answered immediately, so they are not limited.
+        let acquired = match self.config.load_shed {
+            Some(ref load_shed) if cors_preflight.is_none() => load_shed.acquire(),
+            _ => Ok(None),
+        };
+        let (state, permit) = match acquired {
+            Ok(permit) => {
+                #[cfg(feature = "tracing")]
+                let _enter = span.enter();
+                (AppFutureState::Start(Some(self.endpoint.action())), permit)
+            }
+            Err(err) => (AppFutureState::Rejected(Some(err)), None),
+        };
+
         AppFuture {
-            state: AppFutureState::Start(Some(self.endpoint.action())),
-            context: Context::new(Request::from_parts(parts, ())),
+            state,
+            context,
             body: Some(body),
+            config: self.config.clone(),
+            started: Instant::now(),
+            in_flight: self.config.metrics.as_ref().map(Metrics::start),
+            cors_preflight,
+            _permit: permit,
+            _route_permit: None,
+            #[cfg(feature = "tracing")]
+            span,
         }
     }
 }
@@ -103,12 +345,15 @@ impl<Bd, E> Service<Request<Bd>> for AppService<Bd, E>
 where
     E: Endpoint<Bd> + Clone,
     E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
 {
     type Response = Response<ResponseBody<Bd, E>>;
     type Error = io::Error;
     type Future = AppFuture<Bd, E>;
 
     fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        // The service never waits for the capacity, since the requests exceeding
+        // the limits of `LoadShed` are rejected in `call`.
         Ok(Async::Ready(()))
     }
 
@@ -123,12 +368,21 @@ pub struct AppFuture<Bd, E: Endpoint<Bd>> {
     state: AppFutureState<E::Action>,
     context: Context,
     body: Option<Bd>,
+    config: Arc<Config>,
+    started: Instant,
+    in_flight: Option<InFlight>,
+    cors_preflight: Option<Response<()>>,
+    _permit: Option<Permit>,
+    _route_permit: Option<Permit>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 #[allow(missing_debug_implementations, clippy::large_enum_variant)]
 enum AppFutureState<A> {
     Start(Option<A>),
     InFlight(A),
+    Rejected(Option<Error>),
 }
 
 impl<Bd, E> AppFuture<Bd, E>
@@ -136,12 +390,44 @@ where
     E: Endpoint<Bd>,
 {
     pub(crate) fn poll_apply(&mut self) -> Poll<E::Output, Error> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        if !self.config.catch_unwind {
+            return self.poll_apply_inner();
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| self.poll_apply_inner())) {
+            Ok(polled) => polled,
+            Err(payload) => {
+                log::error!(
+                    "panicked during handling the request `{} {}`: {}",
+                    self.context.method(),
+                    self.context.uri(),
+                    panic_message(&*payload),
+                );
+                Err(crate::error::internal_server_error(
+                    "the server panicked while handling the request",
+                ))
+            }
+        }
+    }
+
+    fn poll_apply_inner(&mut self) -> Poll<E::Output, Error> {
         loop {
             self.state = match self.state {
                 AppFutureState::Start(ref mut action) => {
                     let mut action = action.take().unwrap();
                     let mut ecx = PreflightContext::new(&self.context);
-                    if let Preflight::Completed(output) = action.preflight(&mut ecx)? {
+                    let preflight = action.preflight(&mut ecx);
+                    self.context.route = ecx.into_route();
+                    let preflight = preflight?;
+                    if let Some(ref load_shed) = self.config.load_shed {
+                        self._route_permit = load_shed.acquire_route(self.context.route())?;
+                    }
+                    if let Preflight::Completed(output) = preflight {
                         return Ok(Async::Ready(output));
                     }
                     AppFutureState::InFlight(action)
@@ -152,6 +438,9 @@ where
                         &mut self.body,
                     ));
                 }
+                AppFutureState::Rejected(ref mut err) => {
+                    return Err(err.take().expect("the future has already been polled"));
+                }
             };
         }
     }
@@ -161,18 +450,30 @@ impl<Bd, E> Future for AppFuture<Bd, E>
 where
     E: Endpoint<Bd>,
     E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
 {
     type Item = Response<ResponseBody<Bd, E>>;
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let mut response = match ready!(self.poll_apply()) {
-            Ok(output) => output
-                .into_response(&self.context.request)
-                .map(izanami_util::buf_stream::Either::Right),
-            Err(err) => err
-                .into_response(&self.context.request)
-                .map(izanami_util::buf_stream::Either::Left),
+        let mut response = match self.cors_preflight.take() {
+            Some(response) => {
+                response.map(|()| izanami_util::buf_stream::Either::Left(String::new()))
+            }
+            None => match ready!(self.poll_apply()) {
+                Ok(output) => output
+                    .into_response(&self.context.request)
+                    .map(izanami_util::buf_stream::Either::Right),
+                Err(err) => err
+                    .into_response_with(&self.context.request, |err, request, _| {
+                        // Attach the request ID so that the clients can report it along with the error.
+                        match request.extensions().get::<RequestId>() {
+                            Some(request_id) => format!("{} (request ID: {})", err, request_id),
+                            None => err.to_string(),
+                        }
+                    })
+                    .map(izanami_util::buf_stream::Either::Left),
+            },
         };
 
         if let Some(cookies) = &self.context.cookies {
@@ -191,10 +492,82 @@ where
             }
         }
 
+        let len = match response.body() {
+            izanami_util::buf_stream::Either::Left(body) => Some(body.len() as u64),
+            izanami_util::buf_stream::Either::Right(body) => body.size_hint().upper(),
+        }
+        .or_else(|| {
+            response
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.parse().ok())
+        });
+
+        #[cfg(feature = "compression")]
+        let (response, len) = match self.config.compression {
+            Some(ref compression) => {
+                let response = compression.encode(&self.context.request, response, len);
+                // The size of the compressed body is unknown until it is sent.
+                let len = if response.body().is_compressed() {
+                    None
+                } else {
+                    len
+                };
+                (response, len)
+            }
+            None => (response.map(Encoded::identity), len),
+        };
+
+        #[cfg(feature = "tracing")]
+        self.span.record("status", response.status().as_u16());
+
+        let latency = self.started.elapsed();
+
+        if let Some(in_flight) = self.in_flight.take() {
+            in_flight.finish(
+                self.context.method(),
+                self.context.route(),
+                response.status(),
+                latency,
+            );
+        }
+
+        if let Some(ref access_log) = self.config.access_log {
+            access_log.log(&access_log::Entry {
+                request: &self.context.request,
+                remote_addr: self.context.remote_addr(),
+                request_id: self.context.request_id(),
+                status: response.status(),
+                response_size: len,
+                latency,
+                timestamp: SystemTime::now() - latency,
+            });
+        }
+
         Ok(Async::Ready(response))
     }
 }
 
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&'static str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.as_str()
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+#[cfg(feature = "compression")]
+pub type ResponseBody<Bd, E> = Encoded<
+    izanami_util::buf_stream::Either<
+        String, //
+        <<E as Endpoint<Bd>>::Output as IntoResponse>::Body,
+    >,
+>;
+
+#[cfg(not(feature = "compression"))]
 pub type ResponseBody<Bd, E> = izanami_util::buf_stream::Either<
     String, //
     <<E as Endpoint<Bd>>::Output as IntoResponse>::Body,
@@ -251,6 +624,10 @@ pub struct Context {
     request: Request<()>,
     cookies: Option<CookieJar>,
     response_headers: Option<HeaderMap>,
+    route: Option<String>,
+    #[cfg(feature = "compression")]
+    decompression: Option<Decompression>,
+    body_limit: Option<u64>,
 }
 
 impl Context {
@@ -259,6 +636,10 @@ impl Context {
             request,
             cookies: None,
             response_headers: None,
+            route: None,
+            #[cfg(feature = "compression")]
+            decompression: None,
+            body_limit: None,
         }
     }
 
@@ -311,6 +692,37 @@ impl Context {
         }
     }
 
+    /// Returns the information about the underlying connection, if available.
+    pub fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.request.extensions().get()
+    }
+
+    /// Returns the address of the client, if available.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.connection_info().and_then(ConnectionInfo::remote_addr)
+    }
+
+    /// Returns the ID assigned to the current request, if available.
+    ///
+    /// The ID is available only if the request ID is enabled by `App::request_id`.
+    pub fn request_id(&self) -> Option<&RequestId> {
+        self.request.extensions().get()
+    }
+
+    /// Returns the route template of the current request, if available.
+    ///
+    /// The template is constructed by concatenating the path literals of `path!()`
+    /// matched to the request, e.g. `/api/posts/<i32>`. It is available after
+    /// the routing has been completed.
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_ref().map(String::as_str)
+    }
+
+    /// Returns the default maximum length of the request body configured by `App::body_limit`.
+    pub(crate) fn body_limit(&self) -> Option<u64> {
+        self.body_limit
+    }
+
     /// Returns a mutable reference to a `HeaderMap` which contains the supplemental response headers.
     pub fn response_headers(&mut self) -> &mut HeaderMap {
         self.response_headers.get_or_insert_with(Default::default)
@@ -332,3 +744,160 @@ impl std::ops::DerefMut for Context {
         self.request_mut()
     }
 }
+
+// ==== ConnectionInfo ====
+
+/// The information about the connection on which a request has been received.
+///
+/// The server is responsible for storing the value of this type into the
+/// extensions of each request, which can be accessed by `Context::connection_info`.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    peer_credentials: Option<PeerCredentials>,
+    tls: Option<Arc<TlsInfo>>,
+}
+
+impl ConnectionInfo {
+    /// Creates an empty `ConnectionInfo`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the address of the remote peer.
+    pub fn with_remote_addr(self, addr: SocketAddr) -> Self {
+        ConnectionInfo {
+            remote_addr: Some(addr),
+            ..self
+        }
+    }
+
+    /// Sets the local address of the connection.
+    pub fn with_local_addr(self, addr: SocketAddr) -> Self {
+        ConnectionInfo {
+            local_addr: Some(addr),
+            ..self
+        }
+    }
+
+    /// Sets the credentials of the peer process.
+    pub fn with_peer_credentials(self, credentials: PeerCredentials) -> Self {
+        ConnectionInfo {
+            peer_credentials: Some(credentials),
+            ..self
+        }
+    }
+
+    /// Sets the information about the TLS session.
+    pub fn with_tls(self, tls: TlsInfo) -> Self {
+        ConnectionInfo {
+            tls: Some(Arc::new(tls)),
+            ..self
+        }
+    }
+
+    /// Returns the address of the remote peer, if available.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the local address of the connection, if available.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the credentials of the peer process, if available.
+    ///
+    /// The credentials are available only if the connection has been established
+    /// through a Unix domain socket.
+    pub fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.peer_credentials
+    }
+
+    /// Returns the information about the TLS session, if the connection is secured by TLS.
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_ref().map(|tls| &**tls)
+    }
+}
+
+/// The credentials of the process connected to the other end of a Unix domain socket.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PeerCredentials {
+    uid: u32,
+    gid: u32,
+}
+
+impl PeerCredentials {
+    /// Creates a new `PeerCredentials` from the specified user ID and group ID.
+    pub fn new(uid: u32, gid: u32) -> Self {
+        PeerCredentials { uid, gid }
+    }
+
+    /// Returns the user ID of the peer process.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the group ID of the peer process.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+}
+
+/// The information about the TLS session negotiated on a connection.
+#[derive(Debug, Clone, Default)]
+pub struct TlsInfo {
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    peer_certificates: Vec<Vec<u8>>,
+}
+
+impl TlsInfo {
+    /// Creates an empty `TlsInfo`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the server name requested by the client via SNI.
+    pub fn with_server_name(self, server_name: impl Into<String>) -> Self {
+        TlsInfo {
+            server_name: Some(server_name.into()),
+            ..self
+        }
+    }
+
+    /// Sets the application protocol negotiated via ALPN.
+    pub fn with_alpn_protocol(self, protocol: impl Into<Vec<u8>>) -> Self {
+        TlsInfo {
+            alpn_protocol: Some(protocol.into()),
+            ..self
+        }
+    }
+
+    /// Sets the DER-encoded certificate chain presented by the client.
+    pub fn with_peer_certificates(self, certificates: Vec<Vec<u8>>) -> Self {
+        TlsInfo {
+            peer_certificates: certificates,
+            ..self
+        }
+    }
+
+    /// Returns the server name requested by the client via SNI, if available.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_ref().map(String::as_str)
+    }
+
+    /// Returns the application protocol negotiated via ALPN, e.g. `b"h2"`, if available.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_ref().map(Vec::as_slice)
+    }
+
+    /// Returns the DER-encoded certificate chain presented by the client.
+    ///
+    /// The end-entity certificate comes first. The chain is empty if the client
+    /// has not sent any certificates.
+    pub fn peer_certificates(&self) -> &[Vec<u8>] {
+        &self.peer_certificates
+    }
+}
@@ -0,0 +1,353 @@
+//! Access logging at the application level.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{access_log::AccessLog, App};
+//!
+//! let endpoint = path!(@get "/").map(|| "Hello");
+//! let app = App::new(endpoint).access_log(AccessLog::combined());
+//! # drop(app);
+//! ```
+
+use {
+    super::request_id::RequestId,
+    http::{header, Request, StatusCode},
+    log::Level,
+    std::{
+        borrow::Cow,
+        fmt,
+        net::SocketAddr,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// The configuration of access logging.
+///
+/// The log entries are emitted through the `log` crate, with the target
+/// `finchers::access` and the level `Info` by default.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    format: Format,
+    target: Cow<'static, str>,
+    level: Level,
+}
+
+#[derive(Clone)]
+enum Format {
+    Common,
+    Combined,
+    Json,
+    Custom(Arc<dyn Fn(&Entry<'_>) -> String + Send + Sync + 'static>),
+}
+
+impl fmt::Debug for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Common => f.write_str("Common"),
+            Format::Combined => f.write_str("Combined"),
+            Format::Json => f.write_str("Json"),
+            Format::Custom(..) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl AccessLog {
+    fn new(format: Format) -> Self {
+        AccessLog {
+            format,
+            target: Cow::Borrowed("finchers::access"),
+            level: Level::Info,
+        }
+    }
+
+    /// Creates an `AccessLog` which formats the entries in the Common Log Format.
+    pub fn common() -> Self {
+        Self::new(Format::Common)
+    }
+
+    /// Creates an `AccessLog` which formats the entries in the Combined Log Format.
+    pub fn combined() -> Self {
+        Self::new(Format::Combined)
+    }
+
+    /// Creates an `AccessLog` which formats the entries as JSON objects.
+    pub fn json() -> Self {
+        Self::new(Format::Json)
+    }
+
+    /// Creates an `AccessLog` which formats the entries with the specified function.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&Entry<'_>) -> String + Send + Sync + 'static,
+    {
+        Self::new(Format::Custom(Arc::new(f)))
+    }
+
+    /// Sets the target of log records.
+    pub fn target(self, target: impl Into<Cow<'static, str>>) -> Self {
+        AccessLog {
+            target: target.into(),
+            ..self
+        }
+    }
+
+    /// Sets the level of log records.
+    pub fn level(self, level: Level) -> Self {
+        AccessLog { level, ..self }
+    }
+
+    /// Formats the specified entry according to this configuration.
+    pub fn format(&self, entry: &Entry<'_>) -> String {
+        match self.format {
+            Format::Common => entry.to_common(),
+            Format::Combined => entry.to_combined(),
+            Format::Json => entry.to_json(),
+            Format::Custom(ref f) => f(entry),
+        }
+    }
+
+    pub(crate) fn log(&self, entry: &Entry<'_>) {
+        if log::log_enabled!(target: &*self.target, self.level) {
+            log::log!(target: &*self.target, self.level, "{}", self.format(entry));
+        }
+    }
+}
+
+/// A set of values recorded for each request.
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub(crate) request: &'a Request<()>,
+    pub(crate) remote_addr: Option<SocketAddr>,
+    pub(crate) request_id: Option<&'a RequestId>,
+    pub(crate) status: StatusCode,
+    pub(crate) response_size: Option<u64>,
+    pub(crate) latency: Duration,
+    pub(crate) timestamp: SystemTime,
+}
+
+impl<'a> Entry<'a> {
+    /// Returns a reference to the request, without the message body.
+    pub fn request(&self) -> &Request<()> {
+        self.request
+    }
+
+    /// Returns the address of the client, if available.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the ID assigned to the request, if available.
+    pub fn request_id(&self) -> Option<&RequestId> {
+        self.request_id
+    }
+
+    /// Returns the status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the size of response body, if it is known before sending.
+    ///
+    /// The size is unknown for the streaming or compressed bodies.
+    pub fn response_size(&self) -> Option<u64> {
+        self.response_size
+    }
+
+    /// Returns the elapsed time from receiving the request to creating the response.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Returns the time when the request was received.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    fn header(&self, name: header::HeaderName) -> Option<&str> {
+        self.request
+            .headers()
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+    }
+
+    fn request_line(&self) -> String {
+        format!(
+            "{} {} {:?}",
+            self.request.method(),
+            self.request
+                .uri()
+                .path_and_query()
+                .map_or("/", |p| p.as_str()),
+            self.request.version()
+        )
+    }
+
+    /// Formats this entry in the Common Log Format.
+    pub fn to_common(&self) -> String {
+        format!(
+            "{} - - [{}] \"{}\" {} {}",
+            self.remote_addr
+                .map_or_else(|| "-".into(), |addr| addr.ip().to_string()),
+            HttpDate(self.timestamp).clf(),
+            escape(&self.request_line()),
+            self.status.as_u16(),
+            self.response_size
+                .map_or_else(|| "-".into(), |size| size.to_string()),
+        )
+    }
+
+    /// Formats this entry in the Combined Log Format.
+    pub fn to_combined(&self) -> String {
+        format!(
+            "{} \"{}\" \"{}\"",
+            self.to_common(),
+            escape(self.header(header::REFERER).unwrap_or("-")),
+            escape(self.header(header::USER_AGENT).unwrap_or("-")),
+        )
+    }
+
+    /// Formats this entry as a JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "timestamp": HttpDate(self.timestamp).rfc3339(),
+            "remote_addr": self.remote_addr.map(|addr| addr.to_string()),
+            "request_id": self.request_id.map(RequestId::as_str),
+            "method": self.request.method().as_str(),
+            "path": self.request.uri().path(),
+            "query": self.request.uri().query(),
+            "version": format!("{:?}", self.request.version()),
+            "status": self.status.as_u16(),
+            "response_size": self.response_size,
+            "latency_ms": self.latency.as_secs() as f64 * 1e3
+                + f64::from(self.latency.subsec_nanos()) / 1e6,
+            "referer": self.header(header::REFERER),
+            "user_agent": self.header(header::USER_AGENT),
+        })
+        .to_string()
+    }
+}
+
+fn escape(s: &str) -> Cow<'_, str> {
+    if s.contains(&['"', '\\'][..]) {
+        Cow::Owned(s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+struct HttpDate(SystemTime);
+
+impl HttpDate {
+    /// Splits the timestamp into (year, month, day, hour, min, sec) in UTC.
+    fn to_civil(&self) -> (i64, u32, u32, u32, u32, u32) {
+        let secs = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        // The timestamps before the epoch are clamped, so the values below are not negative.
+        let (days, rem) = (secs / 86400, secs % 86400);
+
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        (
+            year,
+            month,
+            day,
+            (rem / 3600) as u32,
+            (rem % 3600 / 60) as u32,
+            (rem % 60) as u32,
+        )
+    }
+
+    fn clf(&self) -> String {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let (year, month, day, hour, min, sec) = self.to_civil();
+        format!(
+            "{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000",
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            hour,
+            min,
+            sec
+        )
+    }
+
+    fn rfc3339(&self) -> String {
+        let (year, month, day, hour, min, sec) = self.to_civil();
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, hour, min, sec
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(request: &Request<()>) -> Entry<'_> {
+        Entry {
+            request,
+            remote_addr: Some(([127, 0, 0, 1], 51234).into()),
+            request_id: None,
+            status: StatusCode::OK,
+            response_size: Some(13),
+            latency: Duration::from_millis(12),
+            timestamp: UNIX_EPOCH + Duration::from_secs(971_186_136),
+        }
+    }
+
+    #[test]
+    fn test_common_log_format() {
+        let request = Request::get("/posts/42?draft=true").body(()).unwrap();
+        assert_eq!(
+            entry(&request).to_common(),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /posts/42?draft=true HTTP/1.1\" 200 13"
+        );
+    }
+
+    #[test]
+    fn test_combined_log_format() {
+        let request = Request::get("/")
+            .header("referer", "http://www.example.com/")
+            .header("user-agent", "curl/7.61.0")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            entry(&request).to_combined(),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET / HTTP/1.1\" 200 13 \
+             \"http://www.example.com/\" \"curl/7.61.0\""
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let request = Request::post("/posts").body(()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&entry(&request).to_json()).unwrap();
+        assert_eq!(value["timestamp"], "2000-10-10T13:55:36Z");
+        assert_eq!(value["remote_addr"], "127.0.0.1:51234");
+        assert_eq!(value["request_id"], serde_json::Value::Null);
+        assert_eq!(value["method"], "POST");
+        assert_eq!(value["path"], "/posts");
+        assert_eq!(value["status"], 200);
+        assert_eq!(value["response_size"], 13);
+        assert_eq!(value["latency_ms"], 12.0);
+    }
+}
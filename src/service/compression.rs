@@ -0,0 +1,493 @@
+//! Compression of the response bodies.
+//!
+//! The encoding is negotiated from the `Accept-Encoding` header of the request,
+//! and the response body is compressed while it is being streamed to the client.
+//! The compression can be applied to the whole application by `App::compression`,
+//! or to the output of an endpoint by `EndpointExt::compress`.
+//!
+//! The responses are sent without compression in the following cases:
+//!
+//! * the client does not accept any of the enabled encodings;
+//! * the response already has `Content-Encoding`, or has no body (e.g. `204 No Content`);
+//! * the response is a part of the representation, i.e. `206 Partial Content` or
+//!   any other response with `Content-Range`;
+//! * the media type of the response is already compressed, such as images and archives;
+//! * the length of the body is known and shorter than the configured minimum size.
+//!
+//! The strong `ETag` of a compressed response is turned into the weak one, since
+//! the compressed body is no longer byte-for-byte identical to the original.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{compression::{Compression, Encoding}, App};
+//!
+//! let endpoint = path!(@get "/posts").map(|| "posts");
+//!
+//! let compression = Compression::new()
+//!     .encodings(vec![Encoding::Gzip, Encoding::Deflate])
+//!     .min_size(256);
+//!
+//! let app = App::new(endpoint).compression(compression);
+//! # drop(app);
+//! ```
+
+use {
+    crate::output::IntoResponse,
+    brotli::CompressorWriter,
+    bytes::{Buf, Bytes},
+    flate2::write::{GzEncoder, ZlibEncoder},
+    futures::{Async, Poll},
+    http::{
+        header::{self, HeaderMap, HeaderValue},
+        Method, Request, Response, StatusCode,
+    },
+    izanami_util::buf_stream::{BufStream, SizeHint},
+    std::{fmt, io, io::Write, sync::Arc},
+};
+
+/// A content coding used to compress the response bodies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// `gzip`
+    Gzip,
+    /// `deflate`, the zlib format defined in RFC 1950.
+    Deflate,
+    /// `br`
+    Brotli,
+}
+
+impl Encoding {
+    /// Returns the name of this encoding used in `Content-Encoding`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+            Some(Encoding::Gzip)
+        } else if name.eq_ignore_ascii_case("deflate") {
+            Some(Encoding::Deflate)
+        } else if name.eq_ignore_ascii_case("br") {
+            Some(Encoding::Brotli)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The configuration of the response compression.
+///
+/// The value of this type is a shared handle, so cloning it is cheap.
+#[derive(Debug, Clone)]
+pub struct Compression {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    encodings: Vec<Encoding>,
+    min_size: u64,
+    level: Option<u32>,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    /// Creates a new `Compression` with all supported encodings enabled.
+    ///
+    /// When the client accepts several encodings with the same quality,
+    /// they are preferred in the order of `br`, `gzip` and `deflate`.
+    pub fn new() -> Self {
+        Compression {
+            inner: Arc::new(Inner {
+                encodings: vec![Encoding::Brotli, Encoding::Gzip, Encoding::Deflate],
+                min_size: 0,
+                level: None,
+            }),
+        }
+    }
+
+    /// Sets the enabled encodings, in the order of preference.
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.inner_mut().encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// Sets the minimum length of the response bodies to be compressed.
+    ///
+    /// The bodies whose length is unknown in advance are always compressed.
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.inner_mut().min_size = min_size;
+        self
+    }
+
+    /// Sets the compression level, from `0` (fastest) to `9` (best).
+    ///
+    /// The default level of each encoding is used if not specified.
+    pub fn level(mut self, level: u32) -> Self {
+        self.inner_mut().level = Some(level.min(9));
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("the configuration has already been shared")
+    }
+
+    /// Chooses the encoding of the response from `Accept-Encoding` of the request.
+    pub(crate) fn negotiate(&self, headers: &HeaderMap) -> Option<Encoding> {
+        let mut wildcard = None;
+        let mut qvalues = vec![];
+        for value in headers.get_all(header::ACCEPT_ENCODING) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(..) => continue,
+            };
+            for item in value.split(',') {
+                let mut params = item.split(';');
+                let name = params.next().unwrap_or("").trim();
+                let q = params
+                    .filter_map(|param| {
+                        let param = param.trim();
+                        if param.starts_with("q=") || param.starts_with("Q=") {
+                            param[2..].trim().parse::<f32>().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .next()
+                    .unwrap_or(1.0);
+                if name == "*" {
+                    wildcard = Some(q);
+                } else if let Some(encoding) = Encoding::from_name(name) {
+                    qvalues.push((encoding, q));
+                }
+            }
+        }
+
+        let mut chosen: Option<(Encoding, f32)> = None;
+        for &encoding in &self.inner.encodings {
+            let q = qvalues
+                .iter()
+                .find(|&&(e, _)| e == encoding)
+                .map(|&(_, q)| q)
+                .or(wildcard)
+                .unwrap_or(0.0);
+            if q > 0.0 && chosen.map_or(true, |(_, best)| q > best) {
+                chosen = Some((encoding, q));
+            }
+        }
+        chosen.map(|(encoding, _)| encoding)
+    }
+
+    /// Compresses the response body if the client accepts one of the enabled encodings.
+    ///
+    /// `len` is the length of the body if it is known in advance.
+    pub(crate) fn encode<B>(
+        &self,
+        request: &Request<()>,
+        response: Response<B>,
+        len: Option<u64>,
+    ) -> Response<Encoded<B>> {
+        let (mut parts, body) = response.into_parts();
+
+        let skipped = request.method() == Method::HEAD
+            || parts.status.is_informational()
+            || parts.status == StatusCode::NO_CONTENT
+            || parts.status == StatusCode::PARTIAL_CONTENT
+            || parts.status == StatusCode::NOT_MODIFIED
+            || parts.headers.contains_key(header::CONTENT_ENCODING)
+            || parts.headers.contains_key(header::CONTENT_RANGE)
+            || !is_compressible(&parts.headers);
+        if skipped {
+            return Response::from_parts(parts, Encoded::identity(body));
+        }
+
+        parts
+            .headers
+            .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+        let encoding = match self.negotiate(request.headers()) {
+            Some(encoding) => encoding,
+            None => return Response::from_parts(parts, Encoded::identity(body)),
+        };
+        if let Some(len) = len {
+            if len < self.inner.min_size || len == 0 {
+                return Response::from_parts(parts, Encoded::identity(body));
+            }
+        }
+
+        parts.headers.remove(header::CONTENT_LENGTH);
+        if let Some(etag) = parts.headers.get_mut(header::ETAG) {
+            if let Some(weak) = weaken_etag(etag) {
+                *etag = weak;
+            }
+        }
+        parts.headers.insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        let encoder = Encoder::new(encoding, self.inner.level);
+        Response::from_parts(parts, Encoded::compressed(body, encoder))
+    }
+}
+
+/// Returns the weak version of the entity tag, or `None` if it is already weak.
+fn weaken_etag(etag: &HeaderValue) -> Option<HeaderValue> {
+    let etag = etag.as_bytes();
+    if etag.starts_with(b"W/") {
+        return None;
+    }
+    let mut weak = Vec::with_capacity(etag.len() + 2);
+    weak.extend_from_slice(b"W/");
+    weak.extend_from_slice(etag);
+    HeaderValue::from_bytes(&weak).ok()
+}
+
+/// Returns whether the media type of the response is worth compressing.
+fn is_compressible(headers: &HeaderMap) -> bool {
+    let content_type = match headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+    {
+        Some(content_type) => content_type,
+        None => return true,
+    };
+    match (
+        content_type.type_().as_str(),
+        content_type.subtype().as_str(),
+    ) {
+        ("image", "svg") => true,
+        ("image", _) | ("audio", _) | ("video", _) => false,
+        ("font", "woff") | ("font", "woff2") => false,
+        ("application", "zip")
+        | ("application", "gzip")
+        | ("application", "x-gzip")
+        | ("application", "x-bzip2")
+        | ("application", "x-xz")
+        | ("application", "x-7z-compressed")
+        | ("application", "x-rar-compressed")
+        | ("application", "zstd")
+        | ("application", "pdf")
+        | ("application", "wasm")
+        | ("application", "octet-stream") => false,
+        _ => true,
+    }
+}
+
+// ==== Compressed ====
+
+/// An output type which compresses the response body of the inner value.
+///
+/// The value of this type is returned from the endpoint created by `EndpointExt::compress`.
+#[derive(Debug)]
+pub struct Compressed<T> {
+    value: T,
+    compression: Compression,
+}
+
+impl<T> Compressed<T> {
+    pub(crate) fn new(value: T, compression: Compression) -> Self {
+        Compressed { value, compression }
+    }
+
+    /// Consumes `self` and returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> IntoResponse for Compressed<T>
+where
+    T: IntoResponse,
+    T::Body: BufStream,
+{
+    type Body = Encoded<T::Body>;
+
+    fn into_response(self, request: &Request<()>) -> Response<Self::Body> {
+        let response = self.value.into_response(request);
+        let len = response.body().size_hint().upper();
+        self.compression.encode(request, response, len)
+    }
+}
+
+// ==== Encoded ====
+
+/// A `BufStream` which compresses the chunks of the inner body.
+///
+/// Each chunk is flushed after being compressed, so that the streaming
+/// responses are delivered to the client without being delayed.
+#[derive(Debug)]
+pub struct Encoded<B> {
+    body: B,
+    encoder: Option<Encoder>,
+}
+
+impl<B> Encoded<B> {
+    pub(crate) fn identity(body: B) -> Self {
+        Encoded {
+            body,
+            encoder: None,
+        }
+    }
+
+    fn compressed(body: B, encoder: Encoder) -> Self {
+        Encoded {
+            body,
+            encoder: Some(encoder),
+        }
+    }
+
+    pub(crate) fn is_compressed(&self) -> bool {
+        self.encoder.is_some()
+    }
+}
+
+impl<B> BufStream for Encoded<B>
+where
+    B: BufStream,
+{
+    type Item = either::Either<B::Item, io::Cursor<Bytes>>;
+    type Error = B::Error;
+
+    fn poll_buf(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let encoder = match self.encoder {
+            Some(ref mut encoder) if !encoder.is_finished() => encoder,
+            Some(..) => return Ok(Async::Ready(None)),
+            None => {
+                return self
+                    .body
+                    .poll_buf()
+                    .map(|polled| polled.map(|chunk| chunk.map(either::Either::Left)));
+            }
+        };
+
+        loop {
+            let compressed = match futures::try_ready!(self.body.poll_buf()) {
+                Some(chunk) => encoder.compress(chunk),
+                None => encoder.finish(),
+            };
+            if !compressed.is_empty() {
+                return Ok(Async::Ready(Some(either::Either::Right(io::Cursor::new(
+                    compressed,
+                )))));
+            }
+            if encoder.is_finished() {
+                return Ok(Async::Ready(None));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.encoder {
+            Some(..) => SizeHint::new(),
+            None => self.body.size_hint(),
+        }
+    }
+}
+
+/// A compressor writing into the memory buffer.
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+    Finished,
+}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoder::Gzip(..) => f.write_str("Gzip"),
+            Encoder::Deflate(..) => f.write_str("Deflate"),
+            Encoder::Brotli(..) => f.write_str("Brotli"),
+            Encoder::Finished => f.write_str("Finished"),
+        }
+    }
+}
+
+impl Encoder {
+    fn new(encoding: Encoding, level: Option<u32>) -> Self {
+        match encoding {
+            Encoding::Gzip => Encoder::Gzip(GzEncoder::new(
+                vec![],
+                level.map_or_else(Default::default, flate2::Compression::new),
+            )),
+            Encoding::Deflate => Encoder::Deflate(ZlibEncoder::new(
+                vec![],
+                level.map_or_else(Default::default, flate2::Compression::new),
+            )),
+            // The quality of Brotli ranges from 0 to 11, and its default is too slow
+            // for the dynamic contents.
+            Encoding::Brotli => Encoder::Brotli(Box::new(CompressorWriter::new(
+                vec![],
+                4096,
+                level.map_or(4, |level| level + level / 4),
+                22,
+            ))),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        match self {
+            Encoder::Finished => true,
+            _ => false,
+        }
+    }
+
+    fn compress(&mut self, mut chunk: impl Buf) -> Bytes {
+        // Writing into `Vec<u8>` never fails.
+        let writer: &mut dyn Write = match self {
+            Encoder::Gzip(encoder) => encoder,
+            Encoder::Deflate(encoder) => encoder,
+            Encoder::Brotli(encoder) => &mut **encoder,
+            Encoder::Finished => unreachable!("the encoder has already been finished"),
+        };
+        while chunk.has_remaining() {
+            let len = {
+                let bytes = chunk.bytes();
+                writer.write_all(bytes).expect("failed to compress");
+                bytes.len()
+            };
+            chunk.advance(len);
+        }
+        writer.flush().expect("failed to compress");
+        self.take_output()
+    }
+
+    fn finish(&mut self) -> Bytes {
+        let output = match std::mem::replace(self, Encoder::Finished) {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Deflate(encoder) => encoder.finish(),
+            Encoder::Brotli(encoder) => Ok(encoder.into_inner()),
+            Encoder::Finished => unreachable!("the encoder has already been finished"),
+        };
+        output.expect("failed to compress").into()
+    }
+
+    fn take_output(&mut self) -> Bytes {
+        let output = match self {
+            Encoder::Gzip(encoder) => encoder.get_mut(),
+            Encoder::Deflate(encoder) => encoder.get_mut(),
+            Encoder::Brotli(encoder) => encoder.get_mut(),
+            Encoder::Finished => unreachable!("the encoder has already been finished"),
+        };
+        std::mem::replace(output, vec![]).into()
+    }
+}
@@ -0,0 +1,332 @@
+//! Cross-Origin Resource Sharing (CORS).
+//!
+//! The policy can be applied to the whole application by `App::cors`, or to an
+//! endpoint subtree by `EndpointExt::cors`. In both cases, the preflight requests
+//! (`OPTIONS` requests with `Access-Control-Request-Method`) are answered
+//! automatically, and the `Access-Control-*` headers are added to the responses
+//! of the actual requests from the allowed origins.
+//!
+//! The requests without the `Origin` header are not affected by the policy.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{cors::Cors, App};
+//! use http::Method;
+//! use std::time::Duration;
+//!
+//! let cors = Cors::new()
+//!     .allow_origin("https://www.example.com")
+//!     .allow_methods(vec![Method::GET, Method::POST])
+//!     .allow_headers(vec!["content-type", "authorization"])
+//!     .allow_credentials(true)
+//!     .max_age(Duration::from_secs(3600));
+//!
+//! let endpoint = path!(@get "/api/posts").map(|| "posts");
+//! let app = App::new(endpoint).cors(cors);
+//! # drop(app);
+//! ```
+
+use {
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        HttpTryFrom, Method, Request, Response, StatusCode,
+    },
+    std::{collections::HashSet, fmt, sync::Arc, time::Duration},
+};
+
+/// A CORS policy.
+///
+/// The value of this type is a shared handle, so cloning it is cheap.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    origins: Option<HashSet<HeaderValue>>,
+    methods: Vec<Method>,
+    headers: Option<HashSet<HeaderName>>,
+    expose_headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    /// Creates a new `Cors` which does not allow any origins.
+    ///
+    /// The methods `GET`, `HEAD` and `POST` are allowed by default.
+    pub fn new() -> Self {
+        Cors {
+            inner: Arc::new(Inner {
+                origins: Some(HashSet::new()),
+                methods: vec![Method::GET, Method::HEAD, Method::POST],
+                headers: Some(HashSet::new()),
+                expose_headers: vec![],
+                credentials: false,
+                max_age: None,
+            }),
+        }
+    }
+
+    /// Allows the requests from any origins.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.inner_mut().origins = None;
+        self
+    }
+
+    /// Allows the requests from the specified origin, e.g. `https://www.example.com`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the specified value is not a valid header value.
+    pub fn allow_origin<T>(mut self, origin: T) -> Self
+    where
+        HeaderValue: HttpTryFrom<T>,
+        <HeaderValue as HttpTryFrom<T>>::Error: fmt::Debug,
+    {
+        let origin = HeaderValue::try_from(origin).expect("invalid origin");
+        if let Some(ref mut origins) = self.inner_mut().origins {
+            origins.insert(origin);
+        }
+        self
+    }
+
+    /// Allows the requests from the specified origins.
+    pub fn allow_origins<I>(self, origins: I) -> Self
+    where
+        I: IntoIterator,
+        HeaderValue: HttpTryFrom<I::Item>,
+        <HeaderValue as HttpTryFrom<I::Item>>::Error: fmt::Debug,
+    {
+        origins
+            .into_iter()
+            .fold(self, |cors, origin| cors.allow_origin(origin))
+    }
+
+    /// Sets the methods allowed for the cross-origin requests.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.inner_mut().methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Allows the clients to send any request headers.
+    pub fn allow_any_header(mut self) -> Self {
+        self.inner_mut().headers = None;
+        self
+    }
+
+    /// Allows the clients to send the specified request headers.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any of the specified values is not a valid header name.
+    pub fn allow_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator,
+        HeaderName: HttpTryFrom<I::Item>,
+        <HeaderName as HttpTryFrom<I::Item>>::Error: fmt::Debug,
+    {
+        let headers = headers
+            .into_iter()
+            .map(|name| HeaderName::try_from(name).expect("invalid header name"));
+        if let Some(ref mut allowed) = self.inner_mut().headers {
+            allowed.extend(headers);
+        }
+        self
+    }
+
+    /// Allows the clients to read the specified response headers.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any of the specified values is not a valid header name.
+    pub fn expose_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator,
+        HeaderName: HttpTryFrom<I::Item>,
+        <HeaderName as HttpTryFrom<I::Item>>::Error: fmt::Debug,
+    {
+        let headers = headers
+            .into_iter()
+            .map(|name| HeaderName::try_from(name).expect("invalid header name"));
+        self.inner_mut().expose_headers.extend(headers);
+        self
+    }
+
+    /// Sets whether to allow the requests with credentials, such as cookies.
+    ///
+    /// The default value is `false`.
+    pub fn allow_credentials(mut self, enabled: bool) -> Self {
+        self.inner_mut().credentials = enabled;
+        self
+    }
+
+    /// Sets how long the results of a preflight request can be cached by the clients.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.inner_mut().max_age = Some(max_age);
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("the policy has already been shared")
+    }
+
+    /// Returns the value of `Access-Control-Allow-Origin` if the origin of the request is allowed.
+    fn allowed_origin(&self, request: &Request<()>) -> Option<HeaderValue> {
+        let origin = request.headers().get(header::ORIGIN)?;
+        match self.inner.origins {
+            None if !self.inner.credentials => Some(HeaderValue::from_static("*")),
+            None => Some(origin.clone()),
+            Some(ref origins) if origins.contains(origin) => Some(origin.clone()),
+            Some(..) => None,
+        }
+    }
+
+    /// Creates the response headers for the actual (non-preflight) request.
+    pub(crate) fn headers(&self, request: &Request<()>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        // The value of `Access-Control-Allow-Origin` depends on `Origin` unless it is `*`.
+        let echo_origin = self.inner.origins.is_some() || self.inner.credentials;
+        if request.headers().contains_key(header::ORIGIN) && echo_origin {
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+        if let Some(origin) = self.allowed_origin(request) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            if self.inner.credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+            if !self.inner.expose_headers.is_empty() {
+                headers.insert(
+                    header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                    join(self.inner.expose_headers.iter().map(HeaderName::as_str)),
+                );
+            }
+        }
+        headers
+    }
+
+    /// Creates the response to the preflight request.
+    ///
+    /// The response is `403 Forbidden` if the origin, method or headers requested
+    /// by the client are not allowed.
+    pub(crate) fn preflight(&self, request: &Request<()>) -> Response<()> {
+        let mut response = Response::new(());
+        let headers = response.headers_mut();
+        headers.append(
+            header::VARY,
+            HeaderValue::from_static(
+                "origin, access-control-request-method, access-control-request-headers",
+            ),
+        );
+
+        let origin = self.allowed_origin(request);
+        let method_allowed =
+            requested_method(request).map_or(false, |method| self.inner.methods.contains(&method));
+        let requested_headers = requested_headers(request);
+        let headers_allowed = match (&self.inner.headers, &requested_headers) {
+            (None, _) | (_, None) => true,
+            (Some(allowed), Some(requested)) => requested
+                .to_str()
+                .map(|requested| {
+                    requested
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .all(|name| {
+                            HeaderName::from_bytes(name.as_bytes())
+                                .map(|name| allowed.contains(&name))
+                                .unwrap_or(false)
+                        })
+                })
+                .unwrap_or(false),
+        };
+
+        let origin = match origin {
+            Some(origin) if method_allowed && headers_allowed => origin,
+            _ => {
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                return response;
+            }
+        };
+
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        let headers = response.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.inner.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            join(self.inner.methods.iter().map(Method::as_str)),
+        );
+        match self.inner.headers {
+            Some(ref allowed) => {
+                if !allowed.is_empty() {
+                    let mut allowed: Vec<&str> = allowed.iter().map(HeaderName::as_str).collect();
+                    allowed.sort();
+                    headers.insert(
+                        header::ACCESS_CONTROL_ALLOW_HEADERS,
+                        join(allowed.into_iter()),
+                    );
+                }
+            }
+            None => {
+                if let Some(requested) = requested_headers {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested);
+                }
+            }
+        }
+        if let Some(max_age) = self.inner.max_age {
+            headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            );
+        }
+        response
+    }
+}
+
+/// Returns whether the request is a CORS preflight request.
+pub(crate) fn is_preflight(request: &Request<()>) -> bool {
+    request.method() == Method::OPTIONS
+        && request.headers().contains_key(header::ORIGIN)
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// Returns the method of the actual request sent after the preflight request.
+pub(crate) fn requested_method(request: &Request<()>) -> Option<Method> {
+    request
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|method| Method::from_bytes(method.as_bytes()).ok())
+}
+
+fn requested_headers(request: &Request<()>) -> Option<HeaderValue> {
+    request
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .cloned()
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> HeaderValue {
+    let joined = values.collect::<Vec<_>>().join(", ");
+    HeaderValue::from_shared(joined.into()).expect("should be a valid header value")
+}
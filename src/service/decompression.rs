@@ -0,0 +1,357 @@
+//! Decompression of the request bodies.
+//!
+//! When enabled by `App::decompression`, the request bodies received by the endpoints
+//! in `endpoints::body` (such as `json()`, `text()` and `urlencoded()`) are decoded
+//! according to the `Content-Encoding` header of the request. The encodings `gzip`,
+//! `deflate` and `br` are supported, and the requests with any other encodings are
+//! rejected with `415 Unsupported Media Type`.
+//!
+//! The size of the decompressed body is limited in order to protect the server from
+//! the "zip bombs", and the requests exceeding the limit are rejected with
+//! `413 Payload Too Large`.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{decompression::Decompression, App};
+//! # use serde::Deserialize;
+//! # #[derive(Deserialize)] struct Post {}
+//!
+//! let endpoint = path!(@post "/posts")
+//!     .and(endpoints::body::json::<Post>())
+//!     .map(|_post: Post| "created");
+//!
+//! let app = App::new(endpoint).decompression(Decompression::new().max_size(1024 * 1024));
+//! # drop(app);
+//! ```
+
+use {
+    super::{compression::Encoding, Context},
+    crate::{
+        endpoints::body::PayloadTooLarge,
+        error::{self, Error, HttpError},
+    },
+    brotli::DecompressorWriter,
+    bytes::{Buf, Bytes},
+    flate2::write::{GzDecoder, ZlibDecoder},
+    futures::{Async, Poll},
+    http::{header, Request, Response, StatusCode},
+    izanami_util::buf_stream::{BufStream, SizeHint},
+    std::{fmt, io, io::Write},
+};
+
+/// The configuration of the request body decompression.
+#[derive(Debug, Clone)]
+pub struct Decompression {
+    encodings: Vec<Encoding>,
+    max_size: u64,
+}
+
+impl Default for Decompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decompression {
+    /// Creates a new `Decompression` with all supported encodings enabled.
+    ///
+    /// The default limit of the decompressed size is 10 MiB.
+    pub fn new() -> Self {
+        Decompression {
+            encodings: vec![Encoding::Gzip, Encoding::Deflate, Encoding::Brotli],
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+
+    /// Sets the encodings accepted by the server.
+    pub fn encodings(self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        Decompression {
+            encodings: encodings.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the decompressed request bodies.
+    pub fn max_size(self, max_size: u64) -> Self {
+        Decompression { max_size, ..self }
+    }
+}
+
+/// Wraps the request body with the decoder specified by `Content-Encoding`,
+/// if the decompression is enabled in the context.
+pub(crate) fn decode<Bd>(body: Bd, cx: &Context) -> Result<Decoded<Bd>, Error> {
+    let config = match cx.decompression {
+        Some(ref config) => config,
+        None => return Ok(Decoded::identity(body)),
+    };
+
+    let mut codings = vec![];
+    for value in cx.headers().get_all(header::CONTENT_ENCODING) {
+        let value = value.to_str().map_err(error::bad_request)?;
+        codings.extend(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity")),
+        );
+    }
+
+    match codings.as_slice() {
+        [] => Ok(Decoded::identity(body)),
+        [coding] => match Encoding::from_name(coding) {
+            Some(encoding) if config.encodings.contains(&encoding) => {
+                Ok(Decoded::new(body, encoding, config.max_size))
+            }
+            _ => Err(UnsupportedEncoding::new(config.encodings.clone()).into()),
+        },
+        _ => Err(UnsupportedEncoding::new(config.encodings.clone()).into()),
+    }
+}
+
+// ==== Decoded ====
+
+/// A `BufStream` which decompresses the chunks of the inner request body.
+///
+/// The decompressed chunks are yielded as soon as they are available, and an error
+/// is returned when the total size exceeds the limit.
+#[derive(Debug)]
+pub struct Decoded<Bd> {
+    body: Bd,
+    decoder: Option<Decoder>,
+}
+
+impl<Bd> Decoded<Bd> {
+    /// Creates a `Decoded` which passes through the chunks of the inner body.
+    pub fn identity(body: Bd) -> Self {
+        Decoded {
+            body,
+            decoder: None,
+        }
+    }
+
+    /// Creates a `Decoded` which decompresses the inner body with the specified encoding.
+    pub fn new(body: Bd, encoding: Encoding, max_size: u64) -> Self {
+        Decoded {
+            body,
+            decoder: Some(Decoder::new(encoding, max_size)),
+        }
+    }
+}
+
+impl<Bd> BufStream for Decoded<Bd>
+where
+    Bd: BufStream,
+    Bd::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type Item = either::Either<Bd::Item, io::Cursor<Bytes>>;
+    type Error = Error;
+
+    fn poll_buf(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let decoder = match self.decoder {
+            Some(ref mut decoder) if !decoder.is_finished() => decoder,
+            Some(..) => return Ok(Async::Ready(None)),
+            None => {
+                return self
+                    .body
+                    .poll_buf()
+                    .map(|polled| polled.map(|chunk| chunk.map(either::Either::Left)))
+                    .map_err(|e| error::from_std(e, StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        };
+
+        loop {
+            let polled = futures::try_ready!(self
+                .body
+                .poll_buf()
+                .map_err(|e| error::from_std(e, StatusCode::INTERNAL_SERVER_ERROR)));
+            let decoded = match polled {
+                Some(chunk) => decoder.decode(chunk)?,
+                None => decoder.finish()?,
+            };
+            if !decoded.is_empty() {
+                return Ok(Async::Ready(Some(either::Either::Right(io::Cursor::new(
+                    decoded,
+                )))));
+            }
+            if decoder.is_finished() {
+                return Ok(Async::Ready(None));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.decoder {
+            Some(..) => SizeHint::new(),
+            None => self.body.size_hint(),
+        }
+    }
+}
+
+/// A decompressor writing into a size-limited memory buffer.
+enum Decoder {
+    Gzip(GzDecoder<Sink>),
+    Deflate(ZlibDecoder<Sink>),
+    Brotli(Box<DecompressorWriter<Sink>>),
+    Finished,
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoder::Gzip(..) => f.write_str("Gzip"),
+            Decoder::Deflate(..) => f.write_str("Deflate"),
+            Decoder::Brotli(..) => f.write_str("Brotli"),
+            Decoder::Finished => f.write_str("Finished"),
+        }
+    }
+}
+
+impl Decoder {
+    fn new(encoding: Encoding, max_size: u64) -> Self {
+        let sink = Sink {
+            buf: vec![],
+            remaining: max_size,
+            limit: max_size,
+        };
+        match encoding {
+            Encoding::Gzip => Decoder::Gzip(GzDecoder::new(sink)),
+            Encoding::Deflate => Decoder::Deflate(ZlibDecoder::new(sink)),
+            Encoding::Brotli => Decoder::Brotli(Box::new(DecompressorWriter::new(sink, 4096))),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        match self {
+            Decoder::Finished => true,
+            _ => false,
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Decoder::Gzip(decoder) => decoder,
+            Decoder::Deflate(decoder) => decoder,
+            Decoder::Brotli(decoder) => &mut **decoder,
+            Decoder::Finished => unreachable!("the decoder has already been finished"),
+        }
+    }
+
+    fn sink(&mut self) -> &mut Sink {
+        match self {
+            Decoder::Gzip(decoder) => decoder.get_mut(),
+            Decoder::Deflate(decoder) => decoder.get_mut(),
+            Decoder::Brotli(decoder) => decoder.get_mut(),
+            Decoder::Finished => unreachable!("the decoder has already been finished"),
+        }
+    }
+
+    fn decode(&mut self, mut chunk: impl Buf) -> Result<Bytes, Error> {
+        while chunk.has_remaining() {
+            let len = {
+                let bytes = chunk.bytes();
+                self.writer().write_all(bytes).map_err(decode_error)?;
+                bytes.len()
+            };
+            chunk.advance(len);
+        }
+        Ok(std::mem::replace(&mut self.sink().buf, vec![]).into())
+    }
+
+    fn finish(&mut self) -> Result<Bytes, Error> {
+        let sink = match std::mem::replace(self, Decoder::Finished) {
+            Decoder::Gzip(decoder) => decoder.finish(),
+            Decoder::Deflate(decoder) => decoder.finish(),
+            Decoder::Brotli(decoder) => decoder
+                .into_inner()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "incomplete stream")),
+            Decoder::Finished => unreachable!("the decoder has already been finished"),
+        }
+        .map_err(decode_error)?;
+        Ok(sink.buf.into())
+    }
+}
+
+fn decode_error(err: io::Error) -> Error {
+    match err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<PayloadTooLarge>())
+    {
+        Some(err) => PayloadTooLarge::new(err.limit()).into(),
+        None => error::bad_request(format!("failed to decode the request body: {}", err)),
+    }
+}
+
+/// A buffer of the decompressed data, which rejects the data exceeding the limit.
+struct Sink {
+    buf: Vec<u8>,
+    remaining: u64,
+    limit: u64,
+}
+
+impl Write for Sink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() as u64 > self.remaining {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                PayloadTooLarge::new(self.limit),
+            ));
+        }
+        self.remaining -= data.len() as u64;
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// ==== errors ====
+
+/// An `HttpError` indicating that the `Content-Encoding` of the request is not supported.
+///
+/// The response is `415 Unsupported Media Type`, with the `Accept-Encoding` header
+/// listing the supported encodings.
+#[derive(Debug)]
+pub struct UnsupportedEncoding {
+    accepted: Vec<Encoding>,
+}
+
+impl UnsupportedEncoding {
+    fn new(accepted: Vec<Encoding>) -> Self {
+        UnsupportedEncoding { accepted }
+    }
+}
+
+impl fmt::Display for UnsupportedEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unsupported content encoding")
+    }
+}
+
+impl std::error::Error for UnsupportedEncoding {}
+
+impl HttpError for UnsupportedEncoding {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+
+    fn to_response(&self, _: &Request<()>) -> Response<()> {
+        let accepted = self
+            .accepted
+            .iter()
+            .map(|encoding| encoding.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut response = Response::new(());
+        *response.status_mut() = self.status_code();
+        response.headers_mut().insert(
+            header::ACCEPT_ENCODING,
+            accepted.parse().expect("should be a valid header value"),
+        );
+        response
+    }
+}
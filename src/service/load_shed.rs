@@ -0,0 +1,262 @@
+//! Load shedding based on the number of requests in flight.
+//!
+//! When the configured limit is reached, the excess requests are rejected
+//! immediately with `503 Service Unavailable` rather than being queued, so that
+//! the server fails fast under bursts. The limits are applied at two levels:
+//!
+//! * globally, when the request is passed to the service created by `App`. The
+//!   service is always ready, so that the idle connections never hold the permits;
+//! * per route, after the route template has been determined by the `path!()`
+//!   endpoints matched to the request (e.g. `/posts/<i32>`).
+//!
+//! The individual endpoints can also be limited by `EndpointExt::concurrency_limit`.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{load_shed::LoadShed, App};
+//! use std::time::Duration;
+//!
+//! let endpoint = path!(@get "/posts/<i32>")
+//!     .map(|id: i32| format!("post {}", id))
+//!     .or(path!(@post "/upload").map(|| "uploaded"));
+//!
+//! let load_shed = LoadShed::new()
+//!     .max_in_flight(1024)
+//!     .max_in_flight_per_route(256)
+//!     .route("/upload", 8)
+//!     .retry_after(Duration::from_secs(5));
+//!
+//! let app = App::new(endpoint).load_shed(load_shed);
+//! # drop(app);
+//! ```
+
+use {
+    crate::{
+        error::{Error, HttpError},
+        util::ceil_secs,
+    },
+    http::{header, HeaderValue, Request, Response, StatusCode},
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    },
+};
+
+/// Returns the default value of `Retry-After` sent with the rejected responses.
+pub(crate) fn default_retry_after() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// The configuration of load shedding.
+///
+/// The value of this type is a shared handle, so the clones refer to the same counters.
+#[derive(Debug, Clone)]
+pub struct LoadShed {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    global: Option<Arc<Semaphore>>,
+    per_route: Option<usize>,
+    route_limits: HashMap<String, usize>,
+    routes: Mutex<HashMap<String, Arc<Semaphore>>>,
+    retry_after: Duration,
+}
+
+impl Default for LoadShed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadShed {
+    /// Creates a new `LoadShed` without any limits.
+    pub fn new() -> Self {
+        LoadShed {
+            inner: Arc::new(Inner {
+                global: None,
+                per_route: None,
+                route_limits: HashMap::new(),
+                routes: Mutex::new(HashMap::new()),
+                retry_after: default_retry_after(),
+            }),
+        }
+    }
+
+    /// Sets the maximum number of requests handled concurrently by the application.
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.inner_mut().global = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Sets the maximum number of requests handled concurrently for each route.
+    ///
+    /// The requests which did not match to any route template are not limited.
+    pub fn max_in_flight_per_route(mut self, max: usize) -> Self {
+        self.inner_mut().per_route = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of requests handled concurrently for the specified route template.
+    ///
+    /// The value overrides the one specified by `max_in_flight_per_route`.
+    pub fn route(mut self, route: impl Into<String>, max: usize) -> Self {
+        self.inner_mut().route_limits.insert(route.into(), max);
+        self
+    }
+
+    /// Sets the value of `Retry-After` sent with the rejected responses.
+    ///
+    /// The default value is 1 second.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.inner_mut().retry_after = retry_after;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("the configuration has already been shared")
+    }
+
+    fn overloaded(&self) -> Error {
+        Overloaded::new(self.inner.retry_after).into()
+    }
+
+    /// Acquires a permit from the global limit.
+    pub(crate) fn acquire(&self) -> Result<Option<Permit>, Error> {
+        match self.inner.global {
+            Some(ref global) => global
+                .try_acquire()
+                .map(Some)
+                .ok_or_else(|| self.overloaded()),
+            None => Ok(None),
+        }
+    }
+
+    /// Acquires a permit from the limit of the specified route.
+    pub(crate) fn acquire_route(&self, route: Option<&str>) -> Result<Option<Permit>, Error> {
+        let route = match route {
+            Some(route) => route,
+            None => return Ok(None),
+        };
+        let max = match self.inner.route_limits.get(route) {
+            Some(&max) => max,
+            None => match self.inner.per_route {
+                Some(max) => max,
+                None => return Ok(None),
+            },
+        };
+        let semaphore = self
+            .inner
+            .routes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(route.to_owned())
+            .or_insert_with(|| Arc::new(Semaphore::new(max)))
+            .clone();
+        semaphore
+            .try_acquire()
+            .map(Some)
+            .ok_or_else(|| self.overloaded())
+    }
+}
+
+// ==== Semaphore ====
+
+/// A counter of the permits which are never waited for.
+#[derive(Debug)]
+pub(crate) struct Semaphore {
+    max: usize,
+    acquired: AtomicUsize,
+}
+
+impl Semaphore {
+    pub(crate) fn new(max: usize) -> Self {
+        Semaphore {
+            max,
+            acquired: AtomicUsize::new(0),
+        }
+    }
+
+    /// Acquires a permit, or returns `None` immediately if no permits are available.
+    pub(crate) fn try_acquire(self: &Arc<Self>) -> Option<Permit> {
+        let mut current = self.acquired.load(Ordering::SeqCst);
+        loop {
+            if current >= self.max {
+                return None;
+            }
+            match self.acquired.compare_exchange(
+                current,
+                current + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(..) => return Some(Permit(self.clone())),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// A permit acquired from `Semaphore`, which is released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit(Arc<Semaphore>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.acquired.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// ==== Overloaded ====
+
+/// An `HttpError` indicating that the request has been rejected due to the concurrency limit.
+///
+/// The response is `503 Service Unavailable` with the `Retry-After` header.
+#[derive(Debug)]
+pub struct Overloaded {
+    retry_after: Duration,
+}
+
+impl Overloaded {
+    pub(crate) fn new(retry_after: Duration) -> Self {
+        Overloaded { retry_after }
+    }
+
+    /// Returns the duration which the client is suggested to wait before retrying.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("too many requests are in flight")
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+impl HttpError for Overloaded {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn to_response(&self, _: &Request<()>) -> Response<()> {
+        let mut response = Response::new(());
+        *response.status_mut() = self.status_code();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(ceil_secs(self.retry_after)),
+        );
+        response
+    }
+}
@@ -0,0 +1,368 @@
+//! Collection of metrics in the Prometheus data model.
+//!
+//! The registry records the following metrics for each request handled by `App`:
+//!
+//! * `http_requests_total` - a counter of the handled requests.
+//! * `http_request_duration_seconds` - a histogram of the latency of requests.
+//! * `http_requests_in_flight` - a gauge of the requests currently being handled.
+//!
+//! The counter and the histogram are labelled by the method, the status code and
+//! the route template constructed from the `path!()` endpoints matched to the request
+//! (e.g. `/posts/<i32>`), rather than the raw path in order to keep the cardinality
+//! bounded. The requests which did not match to any `path!()` are labelled with
+//! the route `unknown`.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::path;
+//! use finchers::service::{metrics::Metrics, App};
+//!
+//! let metrics = Metrics::new();
+//!
+//! let endpoint = path!(@get "/metrics")
+//!     .and(endpoints::metrics::render(metrics.clone()))
+//!     .or(path!(@get "/posts/<i32>").map(|id: i32| format!("post {}", id)));
+//!
+//! let app = App::new(endpoint).metrics(metrics);
+//! # drop(app);
+//! ```
+
+use {
+    http::{Method, StatusCode},
+    std::{
+        borrow::Cow,
+        collections::BTreeMap,
+        fmt::{self, Write},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    },
+};
+
+/// The default buckets of the latency histogram, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The route label used for the requests which did not match to any route template.
+const UNKNOWN_ROUTE: &str = "unknown";
+
+/// A registry of the metrics about the handled requests.
+///
+/// The value of this type is a shared handle, so the clones refer to the same registry.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    namespace: Option<Cow<'static, str>>,
+    buckets: Vec<f64>,
+    in_flight: AtomicUsize,
+    series: Mutex<BTreeMap<Labels, Series>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Labels {
+    route: Cow<'static, str>,
+    method: String,
+    status: u16,
+}
+
+#[derive(Debug)]
+struct Series {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Creates a new empty registry with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Creates a builder for configuring a new registry.
+    pub fn builder() -> Builder {
+        Builder {
+            namespace: None,
+            buckets: DEFAULT_BUCKETS.to_owned(),
+        }
+    }
+
+    pub(crate) fn start(&self) -> InFlight {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Renders the current values of the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.write_to(&mut out)
+            .expect("writing to a String should not fail");
+        out
+    }
+
+    fn write_to(&self, out: &mut String) -> fmt::Result {
+        let inner = &*self.inner;
+        let prefix = match inner.namespace {
+            Some(ref namespace) => format!("{}_", namespace),
+            None => String::new(),
+        };
+        let series = inner.series.lock().unwrap_or_else(|e| e.into_inner());
+
+        writeln!(
+            out,
+            "# HELP {}http_requests_total The total number of HTTP requests.",
+            prefix
+        )?;
+        writeln!(out, "# TYPE {}http_requests_total counter", prefix)?;
+        for (labels, s) in series.iter() {
+            writeln!(
+                out,
+                "{}http_requests_total{{{}}} {}",
+                prefix, labels, s.count
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP {}http_request_duration_seconds The latency of HTTP requests.",
+            prefix
+        )?;
+        writeln!(
+            out,
+            "# TYPE {}http_request_duration_seconds histogram",
+            prefix
+        )?;
+        for (labels, s) in series.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in inner.buckets.iter().zip(&s.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "{}http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    prefix, labels, bound, cumulative
+                )?;
+            }
+            writeln!(
+                out,
+                "{}http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                prefix, labels, s.count
+            )?;
+            writeln!(
+                out,
+                "{}http_request_duration_seconds_sum{{{}}} {}",
+                prefix, labels, s.sum
+            )?;
+            writeln!(
+                out,
+                "{}http_request_duration_seconds_count{{{}}} {}",
+                prefix, labels, s.count
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP {}http_requests_in_flight The number of HTTP requests currently being handled.",
+            prefix
+        )?;
+        writeln!(out, "# TYPE {}http_requests_in_flight gauge", prefix)?;
+        writeln!(
+            out,
+            "{}http_requests_in_flight {}",
+            prefix,
+            inner.in_flight.load(Ordering::SeqCst)
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A builder for creating a `Metrics` with custom settings.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    namespace: Option<Cow<'static, str>>,
+    buckets: Vec<f64>,
+}
+
+impl Builder {
+    /// Sets the prefix of metric names, e.g. `myapp` for `myapp_http_requests_total`.
+    pub fn namespace(mut self, namespace: impl Into<Cow<'static, str>>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Sets the upper bounds of the buckets in the latency histogram, in seconds.
+    ///
+    /// The bounds are sorted in the increasing order, and the bucket `+Inf` is always added.
+    pub fn buckets(mut self, buckets: impl IntoIterator<Item = f64>) -> Self {
+        let mut buckets: Vec<f64> = buckets.into_iter().filter(|b| b.is_finite()).collect();
+        buckets.sort_by(|a, b| a.partial_cmp(b).expect("the bounds must be finite"));
+        buckets.dedup();
+        self.buckets = buckets;
+        self
+    }
+
+    /// Creates a new empty registry with the current settings.
+    pub fn build(self) -> Metrics {
+        Metrics {
+            inner: Arc::new(Inner {
+                namespace: self.namespace,
+                buckets: self.buckets,
+                in_flight: AtomicUsize::new(0),
+                series: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+}
+
+/// A guard which tracks a request currently being handled.
+///
+/// The in-flight gauge is decremented when this value is dropped, even if the
+/// request has been cancelled before completing the response.
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    inner: Arc<Inner>,
+}
+
+impl InFlight {
+    pub(crate) fn finish(
+        self,
+        method: &Method,
+        route: Option<&str>,
+        status: StatusCode,
+        latency: Duration,
+    ) {
+        let labels = Labels {
+            route: route.map_or(Cow::Borrowed(UNKNOWN_ROUTE), |route| {
+                Cow::Owned(route.to_owned())
+            }),
+            method: method.as_str().to_owned(),
+            status: status.as_u16(),
+        };
+        let secs = latency.as_secs() as f64 + f64::from(latency.subsec_nanos()) / 1e9;
+
+        let mut series = self.inner.series.lock().unwrap_or_else(|e| e.into_inner());
+        let num_buckets = self.inner.buckets.len();
+        let s = series.entry(labels).or_insert_with(|| Series {
+            buckets: vec![0; num_buckets],
+            sum: 0.0,
+            count: 0,
+        });
+        if let Some(i) = self.inner.buckets.iter().position(|&bound| secs <= bound) {
+            s.buckets[i] += 1;
+        }
+        s.sum += secs;
+        s.count += 1;
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.inner.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "method=\"{}\",route=\"{}\",status=\"{}\"",
+            escape_label(&self.method),
+            escape_label(&self.route),
+            self.status
+        )
+    }
+}
+
+fn escape_label(s: &str) -> Cow<'_, str> {
+    if s.contains(&['"', '\\', '\n'][..]) {
+        Cow::Owned(
+            s.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n"),
+        )
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::builder()
+            .namespace("app")
+            .buckets(vec![0.1, 0.01])
+            .build();
+
+        let in_flight = metrics.start();
+        metrics.start().finish(
+            &Method::GET,
+            Some("/posts/<i32>"),
+            StatusCode::OK,
+            Duration::from_millis(5),
+        );
+        metrics.start().finish(
+            &Method::GET,
+            Some("/posts/<i32>"),
+            StatusCode::OK,
+            Duration::from_millis(50),
+        );
+        metrics.start().finish(
+            &Method::GET,
+            None,
+            StatusCode::NOT_FOUND,
+            Duration::from_secs(1),
+        );
+
+        let rendered = metrics.render();
+        let lines: Vec<&str> = rendered.lines().collect();
+        let label = "method=\"GET\",route=\"/posts/<i32>\",status=\"200\"";
+        for expected in &[
+            format!("app_http_requests_total{{{}}} 2", label),
+            format!(
+                "app_http_request_duration_seconds_bucket{{{},le=\"0.01\"}} 1",
+                label
+            ),
+            format!(
+                "app_http_request_duration_seconds_bucket{{{},le=\"0.1\"}} 2",
+                label
+            ),
+            format!(
+                "app_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                label
+            ),
+            format!("app_http_request_duration_seconds_count{{{}}} 2", label),
+            "app_http_requests_total{method=\"GET\",route=\"unknown\",status=\"404\"} 1".into(),
+            "app_http_requests_in_flight 1".into(),
+        ] {
+            assert!(lines.contains(&&**expected), "missing line: {}", expected);
+        }
+
+        drop(in_flight);
+        assert!(metrics.render().contains("app_http_requests_in_flight 0"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("/foo"), "/foo");
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,200 @@
+//! Generation and propagation of request IDs.
+//!
+//! When enabled, each request is assigned an ID, taken from the incoming
+//! `X-Request-Id` header or newly generated if the header is missing. The ID is
+//! stored in the request context and echoed back in the response header, so that
+//! the log entries of a single request can be correlated across services.
+//!
+//! # Example
+//!
+//! ```
+//! use finchers::prelude::*;
+//! use finchers::endpoints::request_id;
+//! use finchers::service::{request_id::RequestIdConfig, App};
+//!
+//! let endpoint = request_id::required()
+//!     .map(|id: request_id::RequestId| format!("your request ID is {}", id));
+//! let app = App::new(endpoint).request_id(RequestIdConfig::uuid());
+//! # drop(app);
+//! ```
+
+use {
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    std::{
+        fmt,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// The maximum length of request IDs accepted from the clients.
+const MAX_INCOMING_LEN: usize = 128;
+
+/// An identifier assigned to each request.
+///
+/// The value is guaranteed to be a non-empty string consisting only of visible ASCII characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(HeaderValue);
+
+impl RequestId {
+    fn from_header_value(value: &HeaderValue) -> Option<Self> {
+        let bytes = value.as_bytes();
+        if !bytes.is_empty()
+            && bytes.len() <= MAX_INCOMING_LEN
+            && bytes.iter().all(u8::is_ascii_graphic)
+        {
+            Some(RequestId(value.clone()))
+        } else {
+            None
+        }
+    }
+
+    fn from_string(s: String) -> Self {
+        RequestId(HeaderValue::from_shared(s.into()).expect("should be a valid header value"))
+    }
+
+    /// Returns the string representation of this ID.
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().expect("should be a visible ASCII string")
+    }
+
+    /// Returns the representation of this ID as a header value.
+    pub fn as_header_value(&self) -> &HeaderValue {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The configuration of request IDs.
+#[derive(Debug, Clone)]
+pub struct RequestIdConfig {
+    generator: Generator,
+    header_name: HeaderName,
+    trust_incoming: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Generator {
+    Uuid,
+    Monotonic(Arc<AtomicUsize>),
+}
+
+impl RequestIdConfig {
+    fn new(generator: Generator) -> Self {
+        RequestIdConfig {
+            generator,
+            header_name: HeaderName::from_static("x-request-id"),
+            trust_incoming: true,
+        }
+    }
+
+    /// Creates a `RequestIdConfig` which generates the IDs as random UUIDs (version 4).
+    pub fn uuid() -> Self {
+        Self::new(Generator::Uuid)
+    }
+
+    /// Creates a `RequestIdConfig` which generates the IDs from a monotonically increasing counter.
+    ///
+    /// The counter starts at `1` and is shared by all services created from the same `App`.
+    pub fn monotonic() -> Self {
+        Self::new(Generator::Monotonic(Arc::new(AtomicUsize::new(1))))
+    }
+
+    /// Sets the name of header field used for receiving and echoing the request ID.
+    ///
+    /// The default value is `X-Request-Id`.
+    pub fn header_name(self, header_name: HeaderName) -> Self {
+        RequestIdConfig {
+            header_name,
+            ..self
+        }
+    }
+
+    /// Sets whether to accept the request ID sent by the client.
+    ///
+    /// If disabled, a new ID is always generated and the incoming header is ignored.
+    /// Even if enabled, the incoming value is discarded when it is empty, longer than
+    /// 128 bytes or contains any characters other than visible ASCII.
+    ///
+    /// The default value is `true`.
+    pub fn trust_incoming(self, enabled: bool) -> Self {
+        RequestIdConfig {
+            trust_incoming: enabled,
+            ..self
+        }
+    }
+
+    pub(crate) fn header(&self) -> &HeaderName {
+        &self.header_name
+    }
+
+    pub(crate) fn resolve(&self, headers: &HeaderMap) -> RequestId {
+        if self.trust_incoming {
+            if let Some(id) = headers
+                .get(&self.header_name)
+                .and_then(RequestId::from_header_value)
+            {
+                return id;
+            }
+        }
+        self.generate()
+    }
+
+    fn generate(&self) -> RequestId {
+        match self.generator {
+            Generator::Uuid => RequestId::from_string(uuid::Uuid::new_v4().to_string()),
+            Generator::Monotonic(ref counter) => {
+                RequestId::from_string(counter.fetch_add(1, Ordering::Relaxed).to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_incoming() {
+        let config = RequestIdConfig::monotonic();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("abc-123"));
+        assert_eq!(config.resolve(&headers).as_str(), "abc-123");
+
+        let config = config.trust_incoming(false);
+        assert_eq!(config.resolve(&headers).as_str(), "1");
+    }
+
+    #[test]
+    fn test_discard_invalid_incoming() {
+        let config = RequestIdConfig::monotonic();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("has space"));
+        assert_eq!(config.resolve(&headers).as_str(), "1");
+
+        headers.insert("x-request-id", HeaderValue::from_static(""));
+        assert_eq!(config.resolve(&headers).as_str(), "2");
+
+        let long = "a".repeat(MAX_INCOMING_LEN + 1);
+        headers.insert("x-request-id", HeaderValue::from_str(&long).unwrap());
+        assert_eq!(config.resolve(&headers).as_str(), "3");
+    }
+
+    #[test]
+    fn test_generate_uuid() {
+        let config = RequestIdConfig::uuid();
+        let id1 = config.resolve(&HeaderMap::new());
+        let id2 = config.resolve(&HeaderMap::new());
+        assert_eq!(id1.as_str().len(), 36);
+        assert_ne!(id1, id2);
+    }
+}
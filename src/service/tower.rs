@@ -0,0 +1,182 @@
+//! Interoperability with the Tower ecosystem.
+//!
+//! This module is available only if the feature `tower` is enabled.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use finchers::prelude::*;
+//! use finchers::service::App;
+//! use std::time::Duration;
+//! use tower_timeout::TimeoutLayer;
+//!
+//! let endpoint = endpoint::unit().map(|| "Hello");
+//! let service = App::new(endpoint)
+//!     .with_tower_layer(TimeoutLayer::new(Duration::from_secs(10)));
+//! ```
+
+use {
+    super::{App, AppFuture, AppService, ResponseBody},
+    crate::{endpoint::Endpoint, output::IntoResponse},
+    futures::{future, Async, Poll},
+    http::{Request, Response},
+    izanami_service::{MakeService, Service},
+    izanami_util::buf_stream::BufStream,
+    std::{fmt, io, sync::Arc},
+    tower_layer::Layer,
+};
+
+impl<Bd, E> tower_service::Service<Request<Bd>> for App<E>
+where
+    E: Endpoint<Bd>,
+    E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
+{
+    type Response = Response<ResponseBody<Bd, Arc<E>>>;
+    type Error = io::Error;
+    type Future = AppFuture<Bd, Arc<E>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, request: Request<Bd>) -> Self::Future {
+        AppService::new(self.endpoint.clone(), self.config.clone()).dispatch(request)
+    }
+}
+
+impl<Bd, E> tower_service::Service<Request<Bd>> for AppService<Bd, E>
+where
+    E: Endpoint<Bd> + Clone,
+    E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
+{
+    type Response = Response<ResponseBody<Bd, E>>;
+    type Error = io::Error;
+    type Future = AppFuture<Bd, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, request: Request<Bd>) -> Self::Future {
+        self.dispatch(request)
+    }
+}
+
+impl<E> App<E> {
+    /// Wraps the services created by this `App` with the specified Tower `Layer`.
+    pub fn with_tower_layer<L>(self, layer: L) -> Layered<E, L> {
+        Layered { app: self, layer }
+    }
+}
+
+/// A factory of HTTP services, which wraps the services created by `App` with a Tower `Layer`.
+///
+/// The value of this type is created by `App::with_tower_layer`.
+#[derive(Debug)]
+pub struct Layered<E, L> {
+    app: App<E>,
+    layer: L,
+}
+
+impl<E, L> Layered<E, L> {
+    /// Adds another `Layer` outside of the current one.
+    ///
+    /// The layers are applied in the order of calls, so the layer added last
+    /// receives the requests first.
+    pub fn with_tower_layer<L2>(self, layer: L2) -> Layered<E, Stack<L, L2>> {
+        Layered {
+            app: self.app,
+            layer: Stack {
+                inner: self.layer,
+                outer: layer,
+            },
+        }
+    }
+}
+
+impl<E, L, Ctx, Bd> MakeService<Ctx, Request<Bd>> for Layered<E, L>
+where
+    E: Endpoint<Bd>,
+    E::Output: IntoResponse,
+    <E::Output as IntoResponse>::Body: BufStream,
+    L: Layer<AppService<Bd, Arc<E>>, Request<Bd>>,
+    L::LayerError: fmt::Debug,
+{
+    type Response = L::Response;
+    type Error = L::Error;
+    type Service = Compat<L::Service>;
+    type MakeError = io::Error;
+    type Future = future::FutureResult<Self::Service, Self::MakeError>;
+
+    fn make_service(&self, _: Ctx) -> Self::Future {
+        let service = AppService::new(self.app.endpoint.clone(), self.app.config.clone());
+        future::result(self.layer.layer(service).map(Compat).map_err(layer_error))
+    }
+}
+
+fn layer_error(err: impl fmt::Debug) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!("failed to apply the layer: {:?}", err),
+    )
+}
+
+/// A pair of `Layer`s, which applies `inner` and then `outer`.
+#[derive(Debug, Clone)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<S, Request, Inner, Outer> Layer<S, Request> for Stack<Inner, Outer>
+where
+    Inner: Layer<S, Request>,
+    Inner::LayerError: fmt::Debug,
+    Outer: Layer<Inner::Service, Request>,
+    Outer::LayerError: fmt::Debug,
+{
+    type Response = Outer::Response;
+    type Error = Outer::Error;
+    type LayerError = io::Error;
+    type Service = Outer::Service;
+
+    fn layer(&self, service: S) -> Result<Self::Service, Self::LayerError> {
+        let service = self.inner.layer(service).map_err(layer_error)?;
+        self.outer.layer(service).map_err(layer_error)
+    }
+}
+
+/// A wrapper for using an implementor of Tower's `Service` as an HTTP service.
+#[derive(Debug, Clone)]
+pub struct Compat<S>(S);
+
+impl<S> Compat<S> {
+    /// Creates a `Compat` from the specified Tower `Service`.
+    pub fn new(service: S) -> Self {
+        Compat(service)
+    }
+
+    /// Consumes `self` and returns the inner service.
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S, Request> Service<Request> for Compat<S>
+where
+    S: tower_service::Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.0.poll_ready()
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.0.call(request)
+    }
+}
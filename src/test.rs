@@ -21,20 +21,27 @@ use {
     crate::{
         endpoint::Endpoint,
         error::Error,
-        service::{AppFuture, AppService},
+        output::IntoResponse,
+        service::{
+            access_log::AccessLog, cors::Cors, load_shed::LoadShed, metrics::Metrics,
+            request_id::RequestIdConfig, AppFuture, AppService, Config,
+        },
     },
-    bytes::Bytes,
-    futures::{future, Poll},
+    bytes::{Buf, Bytes, BytesMut},
+    futures::{future, Async, Poll},
     http::{
         header::{self, HeaderMap, HeaderName, HeaderValue},
-        Request, Uri,
+        Request, Response, Uri,
     },
-    izanami_util::buf_stream::BufStream,
+    izanami_util::buf_stream::{BufStream, Either},
     mime::Mime,
-    std::io,
+    std::{io, sync::Arc},
     tokio::runtime::current_thread::Runtime,
 };
 
+#[cfg(feature = "compression")]
+use crate::service::{compression::Compression, decompression::Decompression};
+
 // ====
 
 fn or_insert(headers: &mut HeaderMap, name: HeaderName, value: &'static str) {
@@ -87,6 +94,7 @@ pub struct TestRunner<E> {
     endpoint: E,
     rt: Runtime,
     default_headers: Option<HeaderMap>,
+    config: Arc<Config>,
 }
 
 #[allow(clippy::new_ret_no_self)]
@@ -105,6 +113,7 @@ where
             endpoint,
             rt,
             default_headers: None,
+            config: Arc::new(Config::default()),
         }
     }
 
@@ -114,6 +123,80 @@ where
         self.default_headers.get_or_insert_with(Default::default)
     }
 
+    /// Sets whether to catch the panics occurred inside of the endpoint.
+    ///
+    /// See also the documentation of `App::catch_unwind`.
+    pub fn catch_unwind(&mut self, enabled: bool) -> &mut Self {
+        Arc::make_mut(&mut self.config).catch_unwind = enabled;
+        self
+    }
+
+    /// Sets the configuration of request IDs.
+    ///
+    /// See also the documentation of `App::request_id`.
+    pub fn request_id(&mut self, request_id: RequestIdConfig) -> &mut Self {
+        Arc::make_mut(&mut self.config).request_id = Some(request_id);
+        self
+    }
+
+    /// Sets the registry of metrics.
+    ///
+    /// See also the documentation of `App::metrics`.
+    pub fn metrics(&mut self, metrics: Metrics) -> &mut Self {
+        Arc::make_mut(&mut self.config).metrics = Some(metrics);
+        self
+    }
+
+    /// Sets the configuration of access logging.
+    ///
+    /// See also the documentation of `App::access_log`.
+    pub fn access_log(&mut self, access_log: AccessLog) -> &mut Self {
+        Arc::make_mut(&mut self.config).access_log = Some(access_log);
+        self
+    }
+
+    /// Sets the configuration of load shedding.
+    ///
+    /// See also the documentation of `App::load_shed`.
+    pub fn load_shed(&mut self, load_shed: LoadShed) -> &mut Self {
+        Arc::make_mut(&mut self.config).load_shed = Some(load_shed);
+        self
+    }
+
+    /// Sets the configuration of the response compression.
+    ///
+    /// See also the documentation of `App::compression`.
+    #[cfg(feature = "compression")]
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        Arc::make_mut(&mut self.config).compression = Some(compression);
+        self
+    }
+
+    /// Sets the configuration of the request body decompression.
+    ///
+    /// See also the documentation of `App::decompression`.
+    #[cfg(feature = "compression")]
+    pub fn decompression(&mut self, decompression: Decompression) -> &mut Self {
+        Arc::make_mut(&mut self.config).decompression = Some(decompression);
+        self
+    }
+
+    /// Sets the default maximum length of the request bodies.
+    ///
+    /// See also the documentation of `App::body_limit`.
+    pub fn body_limit(&mut self, limit: impl Into<Option<u64>>) -> &mut Self {
+        Arc::make_mut(&mut self.config).body_limit = limit.into();
+        self
+    }
+
+    /// Sets the CORS policy applied to the whole application.
+    ///
+    /// See also the documentation of `App::cors`.
+    pub fn cors(&mut self, cors: Cors) -> &mut Self {
+        Arc::make_mut(&mut self.config).cors = Some(cors);
+        self
+    }
+
     /// Returns a reference to the instance of `Endpoint` owned by this runner.
     pub fn endpoint(&mut self) -> &mut E {
         &mut self.endpoint
@@ -151,7 +234,7 @@ where
             .prepare_request(request)
             .expect("failed to construct a request");
 
-        let future = AppService::new(&self.endpoint).dispatch(request);
+        let future = AppService::new(&self.endpoint, self.config.clone()).dispatch(request);
 
         f(future, &mut self.rt)
     }
@@ -175,6 +258,40 @@ where
             rt.block_on(future::poll_fn(|| future.poll_apply()))
         })
     }
+
+    /// Applies the given request to the inner endpoint and retrieves the response
+    /// in the same way as the real server, with the whole of message body collected.
+    pub fn perform<Bd>(&mut self, request: impl TestRequest) -> io::Result<Response<Bytes>>
+    where
+        E::Output: IntoResponse<Body = Bd>,
+        Bd: BufStream,
+        Either<String, Bd>: BufStream,
+        <Either<String, Bd> as BufStream>::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.apply_inner(request, |future, rt| {
+            let response = rt.block_on(future)?;
+            let (parts, mut body) = response.into_parts();
+            let mut collected = BytesMut::new();
+            rt.block_on(future::poll_fn(|| loop {
+                match body.poll_buf() {
+                    Ok(Async::Ready(Some(mut buf))) => {
+                        while buf.has_remaining() {
+                            let chunk_len = {
+                                let chunk = buf.bytes();
+                                collected.extend_from_slice(chunk);
+                                chunk.len()
+                            };
+                            buf.advance(chunk_len);
+                        }
+                    }
+                    Ok(Async::Ready(None)) => return Ok(Async::Ready(())),
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err)),
+                }
+            }))?;
+            Ok(Response::from_parts(parts, collected.freeze()))
+        })
+    }
 }
 
 mod imp {
@@ -331,7 +448,7 @@ mod imp {
 mod tests {
     use super::*;
 
-    use crate::endpoint;
+    use crate::endpoint::{self, EndpointExt};
     use matches::assert_matches;
 
     #[test]
@@ -429,4 +546,19 @@ mod tests {
 
         assert!(runner.apply_raw("/").is_ok());
     }
+
+    #[test]
+    fn test_catch_unwind() {
+        let mut runner = runner({
+            endpoint::unit().map(|| -> &'static str {
+                panic!("something went wrong");
+            })
+        });
+        runner.catch_unwind(true);
+
+        assert_matches!(
+            runner.apply("/"),
+            Err(ref err) if err.status_code() == http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
 }
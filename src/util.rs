@@ -1,6 +1,6 @@
 #![allow(missing_docs)]
 
-use std::{error, fmt};
+use std::{error, fmt, time::Duration};
 
 /// A type which has no possible values.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq)]
@@ -30,3 +30,10 @@ impl error::Error for Never {
         match *self {}
     }
 }
+
+/// Converts the duration into the number of seconds sent in `Retry-After` and the like.
+///
+/// The value is rounded up so that the clients never retry too early.
+pub(crate) fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
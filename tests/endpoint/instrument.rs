@@ -0,0 +1,88 @@
+#![cfg(feature = "tracing")]
+
+use finchers::prelude::*;
+use finchers::test;
+use std::sync::{Arc, Mutex};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+#[derive(Debug, Default)]
+struct Span {
+    name: &'static str,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for Span {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields
+            .push((field.name().to_owned(), format!("{:?}", value)));
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    spans: Mutex<Vec<Span>>,
+    entered: Mutex<Vec<&'static str>>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Recorder(Arc<State>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut span = Span {
+            name: attrs.metadata().name(),
+            fields: vec![],
+        };
+        attrs.record(&mut span);
+        let mut spans = self.0.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.0.spans.lock().unwrap();
+        values.record(&mut spans[id.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, id: &Id) {
+        let name = self.0.spans.lock().unwrap()[id.into_u64() as usize - 1].name;
+        self.0.entered.lock().unwrap().push(name);
+    }
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn test_instrument() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let endpoint = endpoint::value("Foo").instrument(tracing::info_span!("foo"));
+        let mut runner = test::runner(endpoint);
+        assert_eq!(runner.apply("/").ok(), Some("Foo"));
+    });
+
+    let spans = recorder.0.spans.lock().unwrap();
+    let request = spans.iter().find(|span| span.name == "request").unwrap();
+    assert!(request
+        .fields
+        .contains(&("method".to_owned(), "GET".to_owned())));
+    assert!(request
+        .fields
+        .contains(&("path".to_owned(), "/".to_owned())));
+
+    let entered = recorder.0.entered.lock().unwrap();
+    assert!(entered.contains(&"request"));
+    assert!(entered.contains(&"foo"));
+}
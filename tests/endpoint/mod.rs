@@ -1,9 +1,11 @@
 mod and;
 mod and_then;
 mod boxed;
+mod instrument;
 mod macros;
 mod map;
 mod or;
 mod or_strict;
+mod rate_limit;
 mod recover;
 mod syntax;
@@ -0,0 +1,97 @@
+use finchers::endpoints::rate_limit::{self, Decision, Policy, Store};
+use finchers::error::Error;
+use finchers::path;
+use finchers::prelude::*;
+use finchers::test;
+use futures::future;
+use http::Request;
+use std::time::Duration;
+
+fn request(key: &str) -> Request<()> {
+    Request::get("/").header("x-api-key", key).body(()).unwrap()
+}
+
+#[test]
+fn test_rate_limit() {
+    let mut runner = test::runner(endpoint::value("Hello").rate_limit(
+        rate_limit::by_header("x-api-key"),
+        Policy::token_bucket(2, Duration::from_secs(60)),
+    ));
+
+    let response = runner.perform(request("alice")).unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["ratelimit-limit"], "2");
+    assert_eq!(response.headers()["ratelimit-remaining"], "1");
+    assert_eq!(response.headers()["ratelimit-reset"], "60");
+
+    let response = runner.perform(request("alice")).unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    let response = runner.perform(request("alice")).unwrap();
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60, "{}", retry_after);
+
+    // The other keys have their own quota.
+    let response = runner.perform(request("bob")).unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The requests without the key are not limited.
+    for _ in 0..3 {
+        let response = runner.perform("/").unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(!response.headers().contains_key("ratelimit-limit"));
+    }
+}
+
+#[test]
+fn test_rate_limit_by_route() {
+    let policy = Policy::sliding_window(1, Duration::from_secs(60));
+    let mut runner = test::runner(
+        path!(@get "/posts/<i32>")
+            .map(|id: i32| format!("post {}", id))
+            .rate_limit(rate_limit::by_route(), policy)
+            .or(path!(@get "/users/<i32>")
+                .map(|id: i32| format!("user {}", id))
+                .rate_limit(rate_limit::by_route(), policy)),
+    );
+
+    assert_eq!(runner.perform("/posts/1").unwrap().status().as_u16(), 200);
+    assert_eq!(runner.perform("/posts/2").unwrap().status().as_u16(), 429);
+    assert_eq!(runner.perform("/users/1").unwrap().status().as_u16(), 200);
+    assert_eq!(runner.perform("/not-found").unwrap().status().as_u16(), 404);
+}
+
+/// A `Store` which rejects all requests.
+struct DenyAll;
+
+impl Store for DenyAll {
+    type Future = future::FutureResult<Decision, Error>;
+
+    fn check(&self, _: &str, policy: &Policy) -> Self::Future {
+        let retry_after = Duration::from_secs(30);
+        future::ok(Decision::denied(policy.limit(), retry_after, retry_after))
+    }
+}
+
+#[test]
+fn test_rate_limit_with_store() {
+    let mut runner = test::runner(endpoint::value("Hello").rate_limit_with_store(
+        rate_limit::by_header("x-api-key"),
+        Policy::token_bucket(2, Duration::from_secs(60)),
+        DenyAll,
+    ));
+
+    let response = runner.perform(request("alice")).unwrap();
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["retry-after"], "30");
+    assert_eq!(response.headers()["ratelimit-remaining"], "0");
+
+    assert_eq!(runner.perform("/").unwrap().status().as_u16(), 200);
+}
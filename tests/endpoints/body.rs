@@ -1,4 +1,5 @@
 use finchers::endpoints::body;
+use finchers::prelude::*;
 use finchers::test;
 use http::Request;
 use matches::assert_matches;
@@ -107,3 +108,305 @@ fn test_body_urlencoded() {
         Err(..)
     );
 }
+
+#[test]
+fn test_body_limit() {
+    let message = "The quick brown fox jumps over the lazy dog";
+
+    let mut runner = test::runner(body::text());
+    runner.body_limit(16);
+
+    let err = runner
+        .apply::<String>(Request::post("/").body(message))
+        .unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 413);
+
+    // The request is rejected in the preflight if `Content-Length` exceeds the limit.
+    let err = runner
+        .apply::<String>(
+            Request::post("/")
+                .header("content-length", message.len().to_string())
+                .body(message),
+        )
+        .unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 413);
+
+    assert_matches!(
+        runner.apply(Request::post("/").body("short")),
+        Ok(ref s) if s == "short"
+    );
+
+    // The limit of the endpoint overrides the default one.
+    let mut runner = test::runner(body::text().limit(1024));
+    runner.body_limit(16);
+    assert_matches!(
+        runner.apply(Request::post("/").body(message)),
+        Ok(ref s) if s == message
+    );
+
+    // The bodies are limited to 2 MiB by default, unless the default limit is removed.
+    let large = vec![b'a'; 2 * 1024 * 1024 + 1];
+    let mut runner = test::runner(body::text());
+    let err = runner
+        .apply::<String>(Request::post("/").body(large.clone()))
+        .unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 413);
+    runner.body_limit(None);
+    assert_matches!(
+        runner.apply(Request::post("/").body(large.clone())),
+        Ok(ref s) if s.len() == large.len()
+    );
+
+    let mut runner = test::runner(body::json::<serde_json::Value>().limit(8));
+    let response = runner
+        .perform(
+            Request::post("/")
+                .header("content-type", "application/json")
+                .body(r#"{ "text": "TRPL2" }"#),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 413);
+}
+
+#[test]
+fn test_body_stream() {
+    use futures::{Future, Stream};
+
+    let message = "The quick brown fox jumps over the lazy dog";
+
+    let mut runner = test::runner(body::stream());
+    let (stream,) = runner.apply_raw(Request::post("/").body(message)).unwrap();
+    let received = stream.concat2().wait().unwrap();
+    assert_eq!(&received[..], message.as_bytes());
+
+    // The body is rejected while being received if it exceeds the limit.
+    let mut runner = test::runner(body::stream().limit(16));
+    let (stream,) = runner.apply_raw(Request::post("/").body(message)).unwrap();
+    let err = stream.concat2().wait().unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 413);
+
+    // The body is taken only once.
+    let mut runner = test::runner(body::stream().and(body::stream()));
+    assert_matches!(runner.apply_raw(Request::post("/").body(message)), Err(..));
+}
+
+#[test]
+fn test_body_parse() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Param {
+        text: String,
+    }
+
+    let mut runner = test::runner(body::parse::<Param>());
+    for &(content_type, body) in &[
+        ("application/json", r#"{ "text": "TRPL2" }"#),
+        ("application/json; charset=utf-8", r#"{ "text": "TRPL2" }"#),
+        // The body is passed to the formats other than urlencoded as is.
+        (
+            "application/json; charset=iso-8859-1",
+            r#"{ "text": "TRPL2" }"#,
+        ),
+        ("application/vnd.api+json", r#"{ "text": "TRPL2" }"#),
+        ("application/x-www-form-urlencoded", "text=TRPL2"),
+    ] {
+        let param = runner
+            .apply(
+                Request::post("/")
+                    .header("content-type", content_type)
+                    .body(body),
+            )
+            .unwrap();
+        assert_eq!(param.text, "TRPL2", "content-type: {}", content_type);
+    }
+
+    // The unsupported or missing content types.
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "text/plain")
+            .body("TRPL2")),
+        Err(ref err) if err.status_code().as_u16() == 415
+    );
+    assert_matches!(
+        runner.apply(Request::post("/").body(r#"{ "text": "TRPL2" }"#)),
+        Err(ref err) if err.status_code().as_u16() == 415
+    );
+
+    // The malformed body.
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "application/json")
+            .body("{")),
+        Err(ref err) if err.status_code().as_u16() == 400
+    );
+
+    // The registered format.
+    let mut runner = test::runner(body::parse::<Param>().format(
+        mime::TEXT_PLAIN,
+        |data: &[u8]| -> Result<_, std::str::Utf8Error> {
+            Ok(Param {
+                text: std::str::from_utf8(data)?.to_owned(),
+            })
+        },
+    ));
+    for &content_type in &[
+        "text/plain; charset=utf-8",
+        "text/plain; charset=iso-8859-1",
+    ] {
+        assert_matches!(
+            runner.apply(Request::post("/")
+                .header("content-type", content_type)
+                .body("TRPL2")),
+            Ok(ref param) if *param == Param { text: "TRPL2".into() }
+        );
+    }
+}
+
+#[test]
+fn test_body_text_charset() {
+    let mut runner = test::runner(body::text());
+
+    let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode("こんにちは");
+    let mut utf16 = vec![0xff, 0xfe];
+    utf16.extend(
+        "héllo"
+            .encode_utf16()
+            .flat_map(|c| vec![c as u8, (c >> 8) as u8]),
+    );
+
+    for (content_type, body, expected) in &[
+        (
+            "text/plain; charset=UTF-8",
+            "héllo".as_bytes().to_vec(),
+            "héllo",
+        ),
+        (
+            "text/plain; charset=Shift_JIS",
+            shift_jis.into_owned(),
+            "こんにちは",
+        ),
+        (
+            "text/plain; charset=iso-8859-1",
+            b"caf\xe9".to_vec(),
+            "café",
+        ),
+        (
+            "text/plain; charset=windows-1252",
+            b"\x93ok\x94".to_vec(),
+            "\u{201c}ok\u{201d}",
+        ),
+        ("text/plain; charset=utf-16", utf16, "héllo"),
+        // The byte order mark is removed.
+        (
+            "text/plain; charset=UTF-8",
+            b"\xEF\xBB\xBFh\xC3\xA9llo".to_vec(),
+            "héllo",
+        ),
+        ("text/plain", b"\xEF\xBB\xBFhello".to_vec(), "hello"),
+    ] {
+        let text: String = runner
+            .apply(
+                Request::post("/")
+                    .header("content-type", *content_type)
+                    .body(body.clone()),
+            )
+            .unwrap();
+        assert_eq!(text, *expected, "content-type: {}", content_type);
+    }
+
+    // The unknown charsets and the malformed bodies.
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "text/plain; charset=x-unknown")
+            .body("hello")),
+        Err(ref err) if err.status_code().as_u16() == 400
+    );
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "text/plain; charset=shift_jis")
+            .body(vec![0x82])),
+        Err(ref err) if err.status_code().as_u16() == 400
+    );
+
+    // Only UTF-8 is accepted in the strict mode.
+    let mut runner = test::runner(body::text().strict_charset());
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "text/plain; charset=UTF-8")
+            .body("hello")),
+        Ok(ref s) if s == "hello"
+    );
+    assert_matches!(
+        runner.apply(Request::post("/")
+            .header("content-type", "text/plain; charset=iso-8859-1")
+            .body("hello")),
+        Err(ref err) if err.status_code().as_u16() == 400
+    );
+}
+
+#[test]
+fn test_body_urlencoded_charset() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Param {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let (name, _, _) = encoding_rs::SHIFT_JIS.encode("山田 太郎");
+    let mut body = String::from("name=");
+    for &b in name.iter() {
+        match b {
+            b' ' => body.push('+'),
+            b => body.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    body.push_str("&tags[]=a%26b&tags[]=c%3Dd");
+    let expected = Param {
+        name: "山田 太郎".into(),
+        tags: vec!["a&b".into(), "c=d".into()],
+    };
+
+    let request = || {
+        Request::post("/")
+            .header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=shift_jis",
+            )
+            .body(body.clone())
+    };
+
+    let mut runner = test::runner(body::urlencoded::<Param>());
+    assert_matches!(runner.apply(request()), Ok(ref param) if *param == expected);
+
+    let mut runner = test::runner(body::parse::<Param>());
+    assert_matches!(runner.apply(request()), Ok(ref param) if *param == expected);
+
+    let mut runner = test::runner(body::urlencoded::<Param>().strict_charset());
+    assert_matches!(
+        runner.apply(request()),
+        Err(ref err) if err.status_code().as_u16() == 400
+    );
+
+    // The delimiters cannot be found in the encodings which are not ASCII-compatible.
+    let utf16: Vec<u8> = "name=a&tags[]=b"
+        .encode_utf16()
+        .flat_map(|c| vec![c as u8, (c >> 8) as u8])
+        .collect();
+    let request = || {
+        Request::post("/")
+            .header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-16le",
+            )
+            .body(utf16.clone())
+    };
+    let mut runner = test::runner(body::urlencoded::<Param>());
+    assert_matches!(
+        runner.apply(request()),
+        Err(ref err) if err.status_code().as_u16() == 415
+    );
+    let mut runner = test::runner(body::parse::<Param>());
+    assert_matches!(
+        runner.apply(request()),
+        Err(ref err) if err.status_code().as_u16() == 415
+    );
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "cbor")]
+
+use super::{assert_received, assert_rejected, assert_sent, param, Param};
+use finchers::endpoints::body;
+use finchers::output::Cbor;
+use finchers::prelude::*;
+use finchers::test;
+
+#[test]
+fn test_body_cbor() {
+    let body = serde_cbor::to_vec(&param()).unwrap();
+
+    let mut runner = test::runner(body::cbor::<Param>());
+    assert_received(
+        &mut runner,
+        &["application/cbor", "application/vnd.example+cbor"],
+        &body,
+    );
+    assert_rejected(
+        &mut runner,
+        "application/json",
+        r#"{ "text": "TRPL2" }"#,
+        415,
+    );
+    assert_rejected(&mut runner, "application/cbor", vec![0x1f], 400);
+
+    // `body::parse()` accepts CBOR when the feature is enabled.
+    let mut runner = test::runner(body::parse::<Param>());
+    assert_received(&mut runner, &["application/cbor"], &body);
+}
+
+#[test]
+fn test_output_cbor() {
+    let mut runner = test::runner(endpoint::unit().map(|| Cbor(param())));
+    assert_sent(runner.perform("/").unwrap(), "application/cbor", |body| {
+        serde_cbor::from_slice(body).unwrap()
+    });
+}
@@ -0,0 +1,77 @@
+//! The fixture and checks shared by the tests of the optional serialization formats.
+
+#![cfg(any(feature = "msgpack", feature = "cbor", feature = "xml"))]
+
+mod cbor;
+mod msgpack;
+mod xml;
+
+use bytes::Bytes;
+use finchers::endpoint::Endpoint;
+use finchers::error::Error;
+use finchers::test::{ReqBody, TestRunner};
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Param {
+    text: String,
+}
+
+fn param() -> Param {
+    Param {
+        text: "TRPL2".into(),
+    }
+}
+
+/// Sends a `POST` request with the specified content type and body.
+fn post<E>(
+    runner: &mut TestRunner<E>,
+    content_type: &str,
+    body: impl Into<Vec<u8>>,
+) -> Result<Param, Error>
+where
+    E: Endpoint<ReqBody, Output = (Param,)>,
+{
+    runner.apply(
+        Request::post("/")
+            .header("content-type", content_type)
+            .body(body.into()),
+    )
+}
+
+/// Checks that `param()` is received from the body sent with each of the content types.
+fn assert_received<E>(runner: &mut TestRunner<E>, content_types: &[&str], body: &[u8])
+where
+    E: Endpoint<ReqBody, Output = (Param,)>,
+{
+    for &content_type in content_types {
+        let received = post(runner, content_type, body).unwrap();
+        assert_eq!(received, param(), "content-type: {}", content_type);
+    }
+}
+
+/// Checks that the body sent with the content type is rejected with the status code.
+fn assert_rejected<E>(
+    runner: &mut TestRunner<E>,
+    content_type: &str,
+    body: impl Into<Vec<u8>>,
+    status: u16,
+) where
+    E: Endpoint<ReqBody, Output = (Param,)>,
+{
+    let err = post(runner, content_type, body).unwrap_err();
+    assert_eq!(
+        err.status_code().as_u16(),
+        status,
+        "content-type: {}",
+        content_type
+    );
+}
+
+/// Checks that the response contains `param()` serialized in the content type.
+fn assert_sent(response: Response<Bytes>, content_type: &str, decode: impl FnOnce(&[u8]) -> Param) {
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], content_type);
+    assert_eq!(decode(&response.body()[..]), param());
+}
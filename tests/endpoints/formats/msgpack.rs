@@ -0,0 +1,42 @@
+#![cfg(feature = "msgpack")]
+
+use super::{assert_received, assert_rejected, assert_sent, param, Param};
+use finchers::endpoints::body;
+use finchers::output::MsgPack;
+use finchers::prelude::*;
+use finchers::test;
+
+#[test]
+fn test_body_msgpack() {
+    let mut runner = test::runner(body::msgpack::<Param>());
+    assert_received(
+        &mut runner,
+        &["application/msgpack", "application/x-msgpack"],
+        &rmp_serde::to_vec(&param()).unwrap(),
+    );
+    assert_rejected(
+        &mut runner,
+        "application/json",
+        r#"{ "text": "TRPL2" }"#,
+        415,
+    );
+    assert_rejected(&mut runner, "application/msgpack", vec![0xc1], 400);
+
+    // `body::parse()` accepts MessagePack when the feature is enabled.
+    let mut runner = test::runner(body::parse::<Param>());
+    assert_received(
+        &mut runner,
+        &["application/msgpack"],
+        &rmp_serde::to_vec_named(&param()).unwrap(),
+    );
+}
+
+#[test]
+fn test_output_msgpack() {
+    let mut runner = test::runner(endpoint::unit().map(|| MsgPack(param())));
+    assert_sent(
+        runner.perform("/").unwrap(),
+        "application/msgpack",
+        |body| rmp_serde::from_slice(body).unwrap(),
+    );
+}
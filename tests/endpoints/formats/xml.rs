@@ -0,0 +1,101 @@
+#![cfg(feature = "xml")]
+
+use super::{assert_received, assert_rejected, assert_sent, param, post, Param};
+use finchers::endpoints::body;
+use finchers::output::Xml;
+use finchers::prelude::*;
+use finchers::test;
+
+#[test]
+fn test_body_xml() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?><Param><text>TRPL2</text></Param>"#;
+
+    let mut runner = test::runner(body::xml::<Param>());
+    assert_received(
+        &mut runner,
+        &[
+            "application/xml",
+            "text/xml",
+            "application/soap+xml",
+            "application/xml; charset=UTF-8",
+        ],
+        xml.as_bytes(),
+    );
+
+    // The byte order mark is skipped.
+    assert_received(
+        &mut runner,
+        &["application/xml"],
+        format!("\u{feff}{}", xml).as_bytes(),
+    );
+
+    assert_rejected(&mut runner, "application/json", xml, 415);
+    assert_rejected(&mut runner, "application/xml", "<Param>", 400);
+}
+
+#[test]
+fn test_body_xml_charset() {
+    let cafe = Param {
+        text: "café".into(),
+    };
+    let latin1 = b"<Param><text>caf\xe9</text></Param>".to_vec();
+    let (shift_jis, _, _) = encoding_rs::SHIFT_JIS
+        .encode(r#"<?xml version="1.0" encoding="Shift_JIS"?><Param><text>café</text></Param>"#);
+    let mut utf16 = vec![0xff, 0xfe];
+    utf16.extend(
+        r#"<?xml version="1.0" encoding="UTF-16"?><Param><text>café</text></Param>"#
+            .encode_utf16()
+            .flat_map(|c| vec![c as u8, (c >> 8) as u8]),
+    );
+
+    let mut runner = test::runner(body::xml::<Param>());
+    for (content_type, body) in &[
+        ("text/xml; charset=ISO-8859-1", latin1.clone()),
+        // The encoding declared in the prolog.
+        ("application/xml", shift_jis.to_vec()),
+        ("application/xml", utf16.clone()),
+        // The byte order mark takes precedence over the other ones.
+        ("text/xml; charset=Shift_JIS", utf16),
+        // The `charset` parameter takes precedence over the prolog.
+        (
+            "text/xml; charset=ISO-8859-1",
+            [
+                &b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?>"[..],
+                &latin1,
+            ]
+            .concat(),
+        ),
+    ] {
+        let received = post(&mut runner, content_type, body.clone()).unwrap();
+        assert_eq!(received, cafe, "content-type: {}", content_type);
+    }
+
+    // The unknown encodings and the malformed bodies.
+    assert_rejected(
+        &mut runner,
+        "text/xml; charset=x-unknown",
+        latin1.clone(),
+        400,
+    );
+    assert_rejected(
+        &mut runner,
+        "application/xml",
+        r#"<?xml version="1.0" encoding="x-unknown"?><Param><text>TRPL2</text></Param>"#,
+        400,
+    );
+    assert_rejected(&mut runner, "text/xml; charset=UTF-8", latin1.clone(), 400);
+
+    // Only UTF-8 is accepted in the strict mode.
+    let mut runner = test::runner(body::xml::<Param>().strict_charset());
+    assert_rejected(&mut runner, "text/xml; charset=ISO-8859-1", latin1, 400);
+}
+
+#[test]
+fn test_output_xml() {
+    let mut runner = test::runner(endpoint::unit().map(|| Xml(param())));
+    assert_sent(
+        runner.perform("/").unwrap(),
+        "application/xml; charset=utf-8",
+        |body| serde_xml_rs::from_str(std::str::from_utf8(body).unwrap()).unwrap(),
+    );
+}
@@ -0,0 +1,60 @@
+use finchers::endpoint::syntax;
+use finchers::endpoints::health::Health;
+use finchers::prelude::*;
+use finchers::test;
+use futures::future;
+use matches::assert_matches;
+
+#[test]
+fn test_health_without_checks() {
+    let mut runner = test::runner(Health::new());
+
+    let response = runner.perform("/").unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "pass", "checks": {} }));
+}
+
+#[test]
+fn test_health_checks() {
+    let mut runner = test::runner(
+        Health::new()
+            .check("config", || Ok::<_, String>(()))
+            .check_async("database", || future::err::<(), _>("connection refused")),
+    );
+
+    let report = runner.apply("/").unwrap();
+    assert!(!report.is_healthy());
+    assert_eq!(
+        report.results().collect::<Vec<_>>(),
+        vec![("config", Ok(())), ("database", Err("connection refused"))]
+    );
+
+    let response = runner.perform("/").unwrap();
+    assert_eq!(response.status().as_u16(), 503);
+
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "status": "fail",
+            "checks": {
+                "config": { "status": "pass" },
+                "database": { "status": "fail", "error": "connection refused" },
+            },
+        })
+    );
+}
+
+#[test]
+fn test_health_routing() {
+    let mut runner =
+        test::runner(syntax::path!(@get "/healthz").and(Health::new()).or(
+            syntax::path!(@get "/readyz").and(Health::new().check("never", || Err("not ready"))),
+        ));
+
+    assert_matches!(runner.perform("/healthz"), Ok(ref res) if res.status() == 200);
+    assert_matches!(runner.perform("/readyz"), Ok(ref res) if res.status() == 503);
+}
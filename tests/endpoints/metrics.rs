@@ -0,0 +1,49 @@
+use finchers::endpoint::syntax::{self, verb};
+use finchers::prelude::*;
+use finchers::service::metrics::Metrics;
+use finchers::test;
+use http::Request;
+
+#[test]
+fn test_metrics() {
+    let metrics = Metrics::new();
+    let mut runner = test::runner(
+        syntax::path!(@get "/metrics")
+            .and(endpoints::metrics::render(metrics.clone()))
+            .or(syntax::path!("/api")
+                .and(syntax::path!("/posts/<i32>"))
+                .map(|id: i32| format!("post {}", id)))
+            .or(syntax::path!("/posts/<i32>")
+                .and(verb::post())
+                .map(|_: i32| "created"))
+            .or(syntax::path!("/posts/<..String>").map(|_: String| "posts")),
+    );
+    runner.metrics(metrics);
+
+    for _ in 0..2 {
+        let response = runner.perform("/api/posts/1").unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let _ = runner.perform("/posts/42").unwrap();
+    let _ = runner.perform(Request::post("/posts/42")).unwrap();
+    let _ = runner.perform("/not-found").unwrap();
+
+    let response = runner.perform("/metrics").unwrap();
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+
+    let body = std::str::from_utf8(response.body()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    for expected in &[
+        "http_requests_total{method=\"GET\",route=\"/api/posts/<i32>\",status=\"200\"} 2",
+        "http_requests_total{method=\"GET\",route=\"/posts/<..String>\",status=\"200\"} 1",
+        "http_requests_total{method=\"POST\",route=\"/posts/<i32>\",status=\"200\"} 1",
+        "http_requests_total{method=\"GET\",route=\"unknown\",status=\"404\"} 1",
+        "http_request_duration_seconds_count{method=\"GET\",route=\"/api/posts/<i32>\",status=\"200\"} 2",
+        "http_requests_in_flight 1",
+    ] {
+        assert!(lines.contains(expected), "missing line: {}", expected);
+    }
+}
@@ -1,5 +1,10 @@
 mod body;
 //mod cookie;
+mod formats;
 mod header;
+mod health;
+mod metrics;
+mod multipart;
 mod query;
+mod request_id;
 //mod upgrade;
@@ -0,0 +1,175 @@
+use finchers::endpoints::body::{self, multipart::Form};
+use finchers::error::Error;
+use finchers::test;
+use futures::{Future, Stream};
+use http::Request;
+
+const BOUNDARY: &str = "finchers-boundary";
+
+fn request(body: &str) -> Request<String> {
+    Request::post("/")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(body.replace("\n", "\r\n"))
+        .unwrap()
+}
+
+fn form_body() -> String {
+    format!(
+        "preamble\n\
+         --{b}\n\
+         Content-Disposition: form-data; name=\"title\"\n\
+         \n\
+         Hello\n\
+         --{b}\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\n\
+         Content-Type: text/plain\n\
+         \n\
+         The quick brown fox\njumps over the lazy dog\n\
+         --{b}\n\
+         Content-Disposition: form-data; name=\"count\"\n\
+         \n\
+         42\n\
+         --{b}--\n\
+         epilogue",
+        b = BOUNDARY
+    )
+}
+
+fn status(result: Result<impl std::fmt::Debug, Error>) -> u16 {
+    result.unwrap_err().status_code().as_u16()
+}
+
+#[test]
+fn test_multipart_stream() {
+    let mut runner = test::runner(body::multipart());
+    let (multipart,) = runner.apply_raw(request(&form_body())).unwrap();
+    let parts = multipart
+        .and_then(|part| {
+            let name = part.name().map(ToOwned::to_owned);
+            let filename = part.filename().map(ToOwned::to_owned);
+            let content_type = part.content_type().map(ToString::to_string);
+            part.concat2()
+                .map(move |content| (name, filename, content_type, content))
+        })
+        .collect()
+        .wait()
+        .unwrap();
+
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[0].0.as_ref().map(|s| &**s), Some("title"));
+    assert_eq!(parts[0].1, None);
+    assert_eq!(&parts[0].3[..], b"Hello");
+    assert_eq!(parts[1].0.as_ref().map(|s| &**s), Some("file"));
+    assert_eq!(parts[1].1.as_ref().map(|s| &**s), Some("a \"b\".txt"));
+    assert_eq!(parts[1].2.as_ref().map(|s| &**s), Some("text/plain"));
+    assert_eq!(
+        &parts[1].3[..],
+        &b"The quick brown fox\r\njumps over the lazy dog"[..]
+    );
+    assert_eq!(&parts[2].3[..], b"42");
+
+    // The unread contents are skipped.
+    let (multipart,) = runner.apply_raw(request(&form_body())).unwrap();
+    let names = multipart
+        .map(|part| part.name().unwrap().to_owned())
+        .collect()
+        .wait()
+        .unwrap();
+    assert_eq!(names, vec!["title", "file", "count"]);
+}
+
+#[test]
+fn test_multipart_errors() {
+    let mut runner = test::runner(body::multipart());
+
+    // Content-Type is not multipart/form-data or lacks the boundary.
+    for content_type in &["text/plain", "multipart/form-data"] {
+        assert_eq!(
+            status(
+                runner.apply_raw(
+                    Request::post("/")
+                        .header("content-type", *content_type)
+                        .body("foo"),
+                )
+            ),
+            400
+        );
+    }
+
+    // The body ends without the closing delimiter.
+    let (multipart,) = runner
+        .apply_raw(request(&format!(
+            "--{}\nContent-Disposition: form-data; name=\"a\"\n\nfoo",
+            BOUNDARY
+        )))
+        .unwrap();
+    let err = multipart
+        .and_then(|part| part.concat2())
+        .collect()
+        .wait()
+        .unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 400);
+}
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct Fields {
+    title: String,
+    count: u32,
+}
+
+#[test]
+fn test_multipart_form() {
+    let temp_dir = std::env::temp_dir().join("finchers-test-multipart-form");
+    std::fs::create_dir_all(&temp_dir).unwrap();
+
+    let mut runner = test::runner(body::multipart_form::<Fields>().temp_dir(&temp_dir));
+    let form: Form<Fields> = runner.apply(request(&form_body())).unwrap();
+    assert_eq!(
+        *form.fields(),
+        Fields {
+            title: "Hello".into(),
+            count: 42,
+        }
+    );
+    assert_eq!(form.files().len(), 1);
+
+    let file = form.file("file").unwrap();
+    assert_eq!(file.filename(), "a \"b\".txt");
+    assert_eq!(file.content_type().unwrap(), &mime::TEXT_PLAIN);
+    assert_eq!(file.size(), 44);
+    assert!(file.path().starts_with(&temp_dir));
+    assert_eq!(
+        std::fs::read_to_string(file.path()).unwrap(),
+        "The quick brown fox\r\njumps over the lazy dog"
+    );
+
+    // The temporary files are removed when dropped.
+    let path = file.path().to_owned();
+    drop(form);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_multipart_form_limits() {
+    let mut runner = test::runner(body::multipart_form::<Fields>().file_limit(16));
+    assert_eq!(status(runner.apply(request(&form_body()))), 413);
+
+    let mut runner = test::runner(body::multipart_form::<Fields>().limit(64));
+    assert_eq!(status(runner.apply(request(&form_body()))), 413);
+
+    let mut runner = test::runner(body::multipart_form::<Fields>().field_limit(4));
+    assert_eq!(status(runner.apply(request(&form_body()))), 413);
+
+    // The text fields are not deserialized into the type.
+    let mut runner = test::runner(body::multipart_form::<Fields>());
+    let body = format!(
+        "--{b}\nContent-Disposition: form-data; name=\"title\"\n\nHello\n--{b}--\n",
+        b = BOUNDARY
+    );
+    let err = runner.apply::<Form<Fields>>(request(&body)).unwrap_err();
+    assert_eq!(err.status_code().as_u16(), 400);
+    assert!(err.find_cause::<serde_qs::Error>().is_some());
+}
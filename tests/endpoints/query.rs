@@ -32,6 +32,12 @@ fn test_query_parse() {
     );
 
     assert_matches!(runner.apply("/"), Err(..));
+
+    // The error from the deserializer is preserved as the cause.
+    assert_matches!(
+        runner.apply("/?count=foo&param=rustlang"),
+        Err(ref err) if err.status_code() == 400 && err.find_cause::<serde_qs::Error>().is_some()
+    );
 }
 
 #[test]
@@ -0,0 +1,63 @@
+use finchers::endpoints::request_id;
+use finchers::prelude::*;
+use finchers::service::request_id::RequestIdConfig;
+use finchers::test;
+use futures::future;
+use http::Request;
+use matches::assert_matches;
+
+#[test]
+fn test_request_id_generated() {
+    let mut runner = test::runner(request_id::required());
+    runner.request_id(RequestIdConfig::monotonic());
+
+    assert_matches!(runner.apply("/"), Ok(ref id) if id.as_str() == "1");
+    assert_matches!(runner.apply("/"), Ok(ref id) if id.as_str() == "2");
+}
+
+#[test]
+fn test_request_id_incoming() {
+    let mut runner = test::runner(request_id::required());
+    runner.request_id(RequestIdConfig::uuid());
+
+    assert_matches!(
+        runner.apply(Request::get("/").header("x-request-id", "abc-123")),
+        Ok(ref id) if id.as_str() == "abc-123"
+    );
+}
+
+#[test]
+fn test_request_id_disabled() {
+    let mut runner = test::runner(request_id::optional());
+    assert_matches!(runner.apply("/"), Ok(None));
+
+    let mut runner = test::runner(request_id::required());
+    assert!(runner.apply("/").is_err());
+}
+
+#[test]
+fn test_request_id_echoed() {
+    let mut runner = test::runner(endpoint::value("Hello"));
+    runner.request_id(RequestIdConfig::monotonic());
+
+    let response = runner.perform("/").unwrap();
+    assert_eq!(response.headers()["x-request-id"], "1");
+
+    let response = runner
+        .perform(Request::get("/").header("x-request-id", "abc-123"))
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "abc-123");
+}
+
+#[test]
+fn test_request_id_in_error_response() {
+    let mut runner = test::runner(
+        endpoint::unit().and_then(|| future::err::<&str, _>(finchers::error::bad_request("oops"))),
+    );
+    runner.request_id(RequestIdConfig::monotonic());
+
+    let response = runner.perform("/").unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response.headers()["x-request-id"], "1");
+    assert_eq!(response.body(), "oops (request ID: 1)");
+}
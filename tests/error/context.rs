@@ -0,0 +1,37 @@
+use finchers::error::{self, ResultExt};
+use http::StatusCode;
+
+#[test]
+fn test_error_context() {
+    let err = error::not_found("missing post").context("failed to load the post");
+    assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(err.to_string(), "failed to load the post");
+
+    let chain: Vec<String> = err.iter_chain().map(|cause| cause.to_string()).collect();
+    assert_eq!(chain, vec!["failed to load the post", "missing post"]);
+    assert_eq!(err.root_cause().to_string(), "missing post");
+}
+
+#[test]
+fn test_result_ext_http_context() {
+    let result: Result<(), _> = Err(error::bad_request("invalid id"));
+    let err = result
+        .http_context("while parsing the path")
+        .with_http_context(|| format!("in handler {}", "get_post"))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(err.iter_chain().count(), 3);
+}
+
+#[test]
+fn test_from_std_error() {
+    let parse_err = "foo".parse::<u32>().unwrap_err();
+    let err = error::from_std(parse_err.clone(), StatusCode::BAD_REQUEST)
+        .context("failed to parse the query");
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        err.find_cause::<std::num::ParseIntError>(),
+        Some(&parse_err)
+    );
+    assert_eq!(err.root_cause().to_string(), parse_err.to_string());
+}
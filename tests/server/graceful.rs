@@ -0,0 +1,94 @@
+use super::send_request;
+use finchers::error::Error;
+use finchers::path;
+use finchers::prelude::*;
+use finchers::server::{self, RuntimeMode};
+use futures::{sync::oneshot, Future};
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::timer::Delay;
+
+fn wait_until(mut cond: impl FnMut() -> bool) {
+    let started = Instant::now();
+    while !cond() {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "timed out waiting for the condition"
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+macro_rules! slow_endpoint {
+    ($delay:expr) => {{
+        let delay = $delay;
+        path!(@get "/slow").and_then(move || {
+            Delay::new(Instant::now() + delay).then(|_| Ok::<_, Error>("done"))
+        })
+    }};
+}
+
+#[test]
+fn test_graceful_shutdown_drains_in_flight_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let server = server::start(slow_endpoint!(Duration::from_millis(500)))
+        .runtime(RuntimeMode::ThreadPool)
+        .shutdown_signal(rx);
+    let in_flight = server.in_flight_requests();
+    let server = thread::spawn(move || server.serve_listener(listener));
+
+    let client = thread::spawn(move || {
+        send_request(
+            addr,
+            "GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n", // keep-alive
+        )
+    });
+
+    wait_until(|| in_flight.get() == 1);
+    tx.send(()).unwrap();
+
+    // The server stops accepting new connections.
+    wait_until(|| TcpStream::connect(addr).is_err());
+
+    // The request in progress is completed, and then the connection is closed.
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\ndone"), "{}", response);
+
+    server.join().unwrap().unwrap();
+    assert_eq!(in_flight.get(), 0);
+}
+
+#[test]
+fn test_graceful_shutdown_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let server = server::start(slow_endpoint!(Duration::from_secs(60)))
+        .runtime(RuntimeMode::CurrentThread)
+        .shutdown_signal(rx)
+        .shutdown_timeout(Duration::from_millis(100));
+    let in_flight = server.in_flight_requests();
+    let server = thread::spawn(move || server.serve_listener(listener));
+
+    let client =
+        thread::spawn(move || send_request(addr, "GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n"));
+
+    wait_until(|| in_flight.get() == 1);
+    let started = Instant::now();
+    tx.send(()).unwrap();
+
+    server.join().unwrap().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    // The connection has been closed without sending the response.
+    let response = client.join().unwrap();
+    assert_eq!(response, "");
+}
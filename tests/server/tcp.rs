@@ -0,0 +1,142 @@
+use super::send_request;
+use finchers::action::{Oneshot, OneshotAction, PreflightContext};
+use finchers::endpoint;
+use finchers::error::Error;
+use finchers::path;
+use finchers::prelude::*;
+use finchers::server::{self, RuntimeMode};
+use finchers::service::{load_shed::LoadShed, App};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+struct RemoteAddr;
+
+impl OneshotAction for RemoteAddr {
+    type Output = (String,);
+
+    fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+        let info = cx.connection_info().expect("missing connection info");
+        Ok((format!(
+            "{} -> {}",
+            info.remote_addr().unwrap(),
+            info.local_addr().unwrap()
+        ),))
+    }
+}
+
+fn remote_addr(
+) -> impl Endpoint<server::RequestBody, Output = (String,), Action = Oneshot<RemoteAddr>> {
+    endpoint::endpoint(|| RemoteAddr.into_action())
+}
+
+#[test]
+fn test_serve_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let endpoint = path!(@post "/echo")
+        .and(endpoints::body::text())
+        .map(|body: String| format!("echo: {}", body))
+        .or(path!(@get "/addr").and(remote_addr()));
+
+    thread::spawn(move || {
+        server::start(endpoint)
+            .runtime(RuntimeMode::CurrentThread)
+            .keep_alive(false)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    let response = send_request(
+        addr,
+        "POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\necho: hello"), "{}", response);
+
+    let response = send_request(addr, "GET /addr HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with(&format!(" -> {}", addr)), "{}", response);
+
+    let response = send_request(addr, "GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn test_serve_thread_pool() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        server::start(endpoint::unit().map(|| "Hello"))
+            .runtime(RuntimeMode::ThreadPool)
+            .http2(false)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    let response = send_request(
+        addr,
+        "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nHello"), "{}", response);
+}
+
+#[test]
+fn test_disable_all_protocols() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let result = server::start(endpoint::unit().map(|| "Hello"))
+        .http1(false)
+        .http2(false)
+        .serve_listener(listener);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_idle_connections_do_not_hold_permits() {
+    const MAX_IN_FLIGHT: usize = 2;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let app = App::new(endpoint::unit().map(|| "Hello"))
+            .load_shed(LoadShed::new().max_in_flight(MAX_IN_FLIGHT));
+        server::Server::new(app)
+            .runtime(RuntimeMode::CurrentThread)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    // The server asks the services for their readiness as soon as the connections
+    // are accepted, so these connections are idle while waiting for the requests.
+    let idle: Vec<TcpStream> = (0..MAX_IN_FLIGHT)
+        .map(|_| TcpStream::connect(addr).unwrap())
+        .collect();
+    thread::sleep(Duration::from_millis(100));
+
+    let response = send_request(
+        addr,
+        "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nHello"), "{}", response);
+
+    for mut stream in idle {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    }
+}
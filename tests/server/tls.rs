@@ -0,0 +1,254 @@
+#![cfg(feature = "tls")]
+
+use finchers::action::{Oneshot, OneshotAction, PreflightContext};
+use finchers::endpoint;
+use finchers::error::Error;
+use finchers::prelude::*;
+use finchers::server::{self, tls::TlsConfig, RuntimeMode};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use rustls::{ClientConfig, ClientSession, Session, StreamOwned};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+struct TlsSummary;
+
+impl OneshotAction for TlsSummary {
+    type Output = (String,);
+
+    fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+        let info = cx.connection_info().expect("missing connection info");
+        let tls = info.tls().expect("missing TLS information");
+        Ok((format!(
+            "sni={} alpn={} certs={}",
+            tls.server_name().unwrap_or("-"),
+            tls.alpn_protocol()
+                .map(String::from_utf8_lossy)
+                .unwrap_or_else(|| "-".into()),
+            tls.peer_certificates().len()
+        ),))
+    }
+}
+
+fn tls_summary(
+) -> impl Endpoint<server::RequestBody, Output = (String,), Action = Oneshot<TlsSummary>> {
+    endpoint::endpoint(|| TlsSummary.into_action())
+}
+
+struct Pki {
+    dir: PathBuf,
+    ca: Certificate,
+}
+
+impl Pki {
+    fn new(name: &str) -> Pki {
+        let dir =
+            std::env::temp_dir().join(format!("finchers-test-tls-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = Certificate::from_params(params).unwrap();
+        fs::write(dir.join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+
+        Pki { dir, ca }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Issues a certificate for `localhost` and writes it to `<name>.pem` and `<name>.key`.
+    fn issue(&self, name: &str) -> Vec<u8> {
+        let cert =
+            Certificate::from_params(CertificateParams::new(vec!["localhost".into()])).unwrap();
+        let pem = cert.serialize_pem_with_signer(&self.ca).unwrap();
+        self.write(&format!("{}.pem", name), &pem);
+        self.write(&format!("{}.key", name), &cert.serialize_private_key_pem());
+        rustls::internal::pemfile::certs(&mut pem.as_bytes()).unwrap()[0]
+            .0
+            .clone()
+    }
+
+    /// Replaces the file atomically, so that the server never reads a partially written file.
+    fn write(&self, name: &str, contents: &str) {
+        let tmp_path = self.path(&format!("{}.tmp", name));
+        fs::write(&tmp_path, contents).unwrap();
+        fs::rename(&tmp_path, self.path(name)).unwrap();
+    }
+
+    fn client_config(&self, client_cert: Option<&str>) -> Arc<ClientConfig> {
+        let mut config = ClientConfig::new();
+        let ca = fs::read(self.path("ca.pem")).unwrap();
+        config.root_store.add_pem_file(&mut &ca[..]).unwrap();
+        config.set_protocols(&[b"http/1.1".to_vec()]);
+        if let Some(name) = client_cert {
+            let cert = fs::read(self.path(&format!("{}.pem", name))).unwrap();
+            let key = fs::read(self.path(&format!("{}.key", name))).unwrap();
+            config.set_single_client_cert(
+                rustls::internal::pemfile::certs(&mut &cert[..]).unwrap(),
+                rustls::internal::pemfile::pkcs8_private_keys(&mut &key[..]).unwrap()[0].clone(),
+            );
+        }
+        Arc::new(config)
+    }
+}
+
+impl Drop for Pki {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Sends a GET request over TLS and returns the response with the certificate presented by the server.
+fn send_tls_request(
+    addr: SocketAddr,
+    config: &Arc<ClientConfig>,
+    path: &str,
+) -> io::Result<(String, Vec<u8>)> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let dns_name = webpki::DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let mut stream = StreamOwned::new(ClientSession::new(config, dns_name), stream);
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    )?;
+
+    let mut response = vec![];
+    let mut buf = [0; 4096];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => response.extend_from_slice(&buf[..n]),
+            // The server has sent `close_notify`.
+            Err(ref err) if err.kind() == io::ErrorKind::ConnectionAborted => break,
+            Err(err) => return Err(err),
+        }
+    }
+    let server_cert = stream.sess.get_peer_certificates().unwrap()[0].0.clone();
+    Ok((String::from_utf8(response).unwrap(), server_cert))
+}
+
+#[test]
+fn test_serve_tls() {
+    let pki = Pki::new("serve");
+    pki.issue("server");
+    pki.issue("client");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let tls = TlsConfig::new(pki.path("server.pem"), pki.path("server.key"))
+        .client_auth_optional(pki.path("ca.pem"));
+    thread::spawn(move || {
+        server::start(tls_summary())
+            .runtime(RuntimeMode::CurrentThread)
+            .tls(tls)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    let (response, _) = send_tls_request(addr, &pki.client_config(None), "/").unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.ends_with("\r\n\r\nsni=localhost alpn=http/1.1 certs=0"),
+        "{}",
+        response
+    );
+
+    let (response, _) = send_tls_request(addr, &pki.client_config(Some("client")), "/").unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.ends_with("\r\n\r\nsni=localhost alpn=http/1.1 certs=1"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn test_client_auth_required() {
+    let pki = Pki::new("client-auth");
+    pki.issue("server");
+    pki.issue("client");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let tls = TlsConfig::new(pki.path("server.pem"), pki.path("server.key"))
+        .client_auth_required(pki.path("ca.pem"));
+    thread::spawn(move || {
+        server::start(tls_summary())
+            .runtime(RuntimeMode::CurrentThread)
+            .tls(tls)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    assert!(send_tls_request(addr, &pki.client_config(None), "/").is_err());
+
+    let (response, _) = send_tls_request(addr, &pki.client_config(Some("client")), "/").unwrap();
+    assert!(
+        response.ends_with("\r\n\r\nsni=localhost alpn=http/1.1 certs=1"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn test_reload_certificate() {
+    let pki = Pki::new("reload");
+    let old_cert = pki.issue("server");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let tls = TlsConfig::new(pki.path("server.pem"), pki.path("server.key"))
+        .reload_interval(Duration::from_millis(10));
+    thread::spawn(move || {
+        server::start(endpoint::unit().map(|| "Hello"))
+            .runtime(RuntimeMode::CurrentThread)
+            .tls(tls)
+            .serve_listener(listener)
+            .unwrap();
+    });
+
+    let (response, server_cert) = send_tls_request(addr, &pki.client_config(None), "/").unwrap();
+    assert!(response.ends_with("\r\n\r\nHello"), "{}", response);
+    assert_eq!(server_cert, old_cert);
+
+    // A certificate which does not match the current private key is not used.
+    pki.issue("other");
+    pki.write(
+        "server.pem",
+        &fs::read_to_string(pki.path("other.pem")).unwrap(),
+    );
+    thread::sleep(Duration::from_millis(100));
+    let (response, server_cert) = send_tls_request(addr, &pki.client_config(None), "/").unwrap();
+    assert!(response.ends_with("\r\n\r\nHello"), "{}", response);
+    assert_eq!(server_cert, old_cert);
+
+    let new_cert = pki.issue("server");
+    assert_ne!(new_cert, old_cert);
+
+    // The certificate is reloaded in the background, so retry until the new one is presented.
+    // A new client configuration is used so that the session is not resumed.
+    let mut server_cert = old_cert;
+    for _ in 0..100 {
+        let (response, cert) = send_tls_request(addr, &pki.client_config(None), "/").unwrap();
+        assert!(response.ends_with("\r\n\r\nHello"), "{}", response);
+        server_cert = cert;
+        if server_cert == new_cert {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(server_cert, new_cert);
+}
@@ -0,0 +1,117 @@
+#![cfg(unix)]
+
+use finchers::action::{Oneshot, OneshotAction, PreflightContext};
+use finchers::endpoint;
+use finchers::error::Error;
+use finchers::prelude::*;
+use finchers::server::{self, RuntimeMode, UnixSocket};
+use futures::sync::oneshot;
+use std::{
+    fs,
+    io::{Read, Write},
+    os::unix::{
+        fs::{MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+struct PeerUid;
+
+impl OneshotAction for PeerUid {
+    type Output = (String,);
+
+    fn preflight(self, cx: &mut PreflightContext<'_>) -> Result<Self::Output, Error> {
+        let info = cx.connection_info().expect("missing connection info");
+        let cred = info.peer_credentials().expect("missing peer credentials");
+        Ok((format!("uid={}", cred.uid()),))
+    }
+}
+
+fn peer_uid() -> impl Endpoint<server::RequestBody, Output = (String,), Action = Oneshot<PeerUid>> {
+    endpoint::endpoint(|| PeerUid.into_action())
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "finchers-test-{}-{}.sock",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn connect(path: &PathBuf) -> UnixStream {
+    let started = Instant::now();
+    loop {
+        match UnixStream::connect(path) {
+            Ok(stream) => return stream,
+            Err(err) => {
+                assert!(started.elapsed() < Duration::from_secs(10), "{}", err);
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_serve_unix() {
+    let path = socket_path("serve");
+
+    // A socket file left by a previous process.
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let socket = UnixSocket::new(&path).permissions(0o600).remove_stale(true);
+    let server = thread::spawn(move || {
+        server::start(peer_uid())
+            .runtime(RuntimeMode::CurrentThread)
+            .shutdown_signal(rx)
+            .serve_unix(socket)
+    });
+
+    let mut stream = connect(&path);
+    let metadata = fs::metadata(&path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.ends_with(&format!("\r\n\r\nuid={}", metadata.uid())),
+        "{}",
+        response
+    );
+
+    tx.send(()).unwrap();
+    server.join().unwrap().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_serve_unix_without_removing_stale_socket() {
+    let path = socket_path("stale");
+    drop(UnixListener::bind(&path).unwrap());
+
+    let result = server::start(peer_uid()).serve_unix(path.as_path());
+    assert!(result.is_err());
+
+    let result = server::start(peer_uid()).serve_unix(UnixSocket::new(&path).permissions(0o600));
+    assert!(result.is_err());
+    // The private directory used for binding must not be left.
+    let dir_name = format!(
+        ".{}.{}.tmp",
+        path.file_name().unwrap().to_str().unwrap(),
+        std::process::id()
+    );
+    assert!(!path.with_file_name(dir_name).exists());
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,60 @@
+use finchers::path;
+use finchers::prelude::*;
+use finchers::service::access_log::AccessLog;
+use finchers::test;
+use std::sync::{Arc, Mutex};
+
+/// A logger which enables only the records emitted by this test.
+struct TestLogger;
+
+impl log::Log for TestLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.target() == "finchers::test::access"
+    }
+
+    fn log(&self, _: &log::Record<'_>) {}
+
+    fn flush(&self) {}
+}
+
+static LOGGER: TestLogger = TestLogger;
+
+#[test]
+fn test_access_log_response_size() {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log::LevelFilter::Info);
+
+    let sizes = Arc::new(Mutex::new(vec![]));
+    let access_log = AccessLog::custom({
+        let sizes = sizes.clone();
+        move |entry| {
+            sizes.lock().unwrap().push(entry.response_size());
+            entry.to_common()
+        }
+    })
+    .target("finchers::test::access");
+
+    let mut runner = test::runner(path!(@get "/hello").map(|| "Hello"));
+    runner.access_log(access_log);
+
+    let response = runner.perform("/hello").unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let response = runner.perform("/").unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    let not_found = response.body().len() as u64;
+
+    assert_eq!(*sizes.lock().unwrap(), vec![Some(5), Some(not_found)]);
+
+    // The size of compressed bodies is not known until they are sent.
+    #[cfg(feature = "compression")]
+    {
+        use finchers::service::compression::Compression;
+
+        runner.compression(Compression::new());
+        let response = runner
+            .perform(http::Request::get("/hello").header("accept-encoding", "gzip"))
+            .unwrap();
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(sizes.lock().unwrap().last(), Some(&None));
+    }
+}
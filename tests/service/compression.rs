@@ -0,0 +1,201 @@
+#![cfg(feature = "compression")]
+
+use bytes::Bytes;
+use finchers::path;
+use finchers::prelude::*;
+use finchers::service::compression::{Compression, Encoding};
+use finchers::test;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use http::{Request, Response};
+use std::io::Read;
+
+fn payload() -> String {
+    "finchers ".repeat(1000)
+}
+
+fn request(uri: &str, accept_encoding: &str) -> Request<()> {
+    Request::get(uri)
+        .header("accept-encoding", accept_encoding)
+        .body(())
+        .unwrap()
+}
+
+fn decode(response: &Response<Bytes>) -> String {
+    let body = &response.body()[..];
+    let mut decoded = String::new();
+    match response.headers().get("content-encoding") {
+        Some(encoding) if encoding == "gzip" => {
+            GzDecoder::new(body).read_to_string(&mut decoded).unwrap();
+        }
+        Some(encoding) if encoding == "deflate" => {
+            ZlibDecoder::new(body).read_to_string(&mut decoded).unwrap();
+        }
+        Some(encoding) if encoding == "br" => {
+            brotli::Decompressor::new(body, 4096)
+                .read_to_string(&mut decoded)
+                .unwrap();
+        }
+        Some(encoding) => panic!("unexpected encoding: {:?}", encoding),
+        None => decoded = String::from_utf8(body.to_vec()).unwrap(),
+    }
+    decoded
+}
+
+#[test]
+fn test_negotiation() {
+    let mut runner = test::runner(path!(@get "/").map(payload));
+    runner.compression(Compression::new());
+
+    for &(accept_encoding, expected) in &[
+        ("gzip", Some("gzip")),
+        ("deflate", Some("deflate")),
+        ("gzip, deflate, br", Some("br")),
+        ("gzip;q=1.0, br;q=0.5", Some("gzip")),
+        ("br;q=0, *", Some("gzip")),
+        ("gzip;q=0", None),
+        ("identity", None),
+        ("compress", None),
+    ] {
+        let response = runner.perform(request("/", accept_encoding)).unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response
+                .headers()
+                .get("content-encoding")
+                .map(|h| h.to_str().unwrap()),
+            expected,
+            "accept-encoding: {}",
+            accept_encoding
+        );
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        assert_eq!(decode(&response), payload());
+        if expected.is_some() {
+            assert!(response.body().len() < payload().len());
+            assert!(!response.headers().contains_key("content-length"));
+        }
+    }
+
+    // The requests without `Accept-Encoding` are responded without compression.
+    let response = runner.perform("/").unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+    assert_eq!(response.body().len(), payload().len());
+}
+
+#[test]
+fn test_enabled_encodings() {
+    let mut runner = test::runner(path!(@get "/").map(payload));
+    runner.compression(Compression::new().encodings(vec![Encoding::Deflate, Encoding::Gzip]));
+
+    let response = runner.perform(request("/", "br, gzip, deflate")).unwrap();
+    assert_eq!(response.headers()["content-encoding"], "deflate");
+    assert_eq!(decode(&response), payload());
+
+    let response = runner.perform(request("/", "br")).unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+}
+
+#[test]
+fn test_weaken_etag() {
+    let mut runner = test::runner(
+        path!(@get "/strong")
+            .map(|| {
+                Response::builder()
+                    .header("etag", "\"v1\"")
+                    .body(payload())
+                    .unwrap()
+            })
+            .or(path!(@get "/weak").map(|| {
+                Response::builder()
+                    .header("etag", "W/\"v1\"")
+                    .body(payload())
+                    .unwrap()
+            })),
+    );
+    runner.compression(Compression::new());
+
+    let response = runner.perform(request("/strong", "gzip")).unwrap();
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(response.headers()["etag"], "W/\"v1\"");
+
+    let response = runner.perform(request("/weak", "gzip")).unwrap();
+    assert_eq!(response.headers()["etag"], "W/\"v1\"");
+
+    // The uncompressed responses keep the strong one.
+    let response = runner.perform(request("/strong", "identity")).unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+    assert_eq!(response.headers()["etag"], "\"v1\"");
+}
+
+#[test]
+fn test_skipped_responses() {
+    let mut runner = test::runner(
+        path!(@get "/image")
+            .map(|| {
+                Response::builder()
+                    .header("content-type", "image/png")
+                    .body(payload())
+                    .unwrap()
+            })
+            .or(path!(@get "/short").map(|| "short"))
+            .or(path!(@get "/encoded").map(|| {
+                Response::builder()
+                    .header("content-encoding", "gzip")
+                    .body(payload())
+                    .unwrap()
+            }))
+            .or(path!(@get "/partial").map(|| {
+                Response::builder()
+                    .status(206)
+                    .header("content-range", "bytes 0-8999/18000")
+                    .body(payload())
+                    .unwrap()
+            }))
+            .or(path!(@get "/range").map(|| {
+                Response::builder()
+                    .status(416)
+                    .header("content-range", "bytes */18000")
+                    .body(payload())
+                    .unwrap()
+            })),
+    );
+    runner.compression(Compression::new().min_size(64));
+
+    let response = runner.perform(request("/image", "gzip")).unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+    assert!(!response.headers().contains_key("vary"));
+    assert_eq!(response.body().len(), payload().len());
+
+    let response = runner.perform(request("/short", "gzip")).unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+    assert_eq!(response.headers()["vary"], "accept-encoding");
+    assert_eq!(response.body(), "short");
+
+    let response = runner.perform(request("/encoded", "br")).unwrap();
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(response.body().len(), payload().len());
+
+    // The ranges are the ones of the uncompressed representation.
+    for uri in &["/partial", "/range"] {
+        let response = runner.perform(request(uri, "gzip")).unwrap();
+        assert!(!response.headers().contains_key("content-encoding"));
+        assert_eq!(response.body().len(), payload().len());
+    }
+}
+
+#[test]
+fn test_endpoint_compress() {
+    let mut runner = test::runner(
+        path!(@get "/compressed")
+            .map(payload)
+            .compress(Compression::new())
+            .or(path!(@get "/plain").map(payload)),
+    );
+
+    let response = runner.perform(request("/compressed", "gzip")).unwrap();
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(decode(&response), payload());
+
+    let response = runner.perform(request("/plain", "gzip")).unwrap();
+    assert!(!response.headers().contains_key("content-encoding"));
+    assert_eq!(decode(&response), payload());
+}
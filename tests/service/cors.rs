@@ -0,0 +1,207 @@
+use finchers::path;
+use finchers::prelude::*;
+use finchers::service::cors::Cors;
+use finchers::test;
+use http::{Method, Request};
+use std::time::Duration;
+
+fn preflight(uri: &str, origin: &str, method: &str) -> http::request::Builder {
+    let mut request = Request::builder();
+    request
+        .method(Method::OPTIONS)
+        .uri(uri)
+        .header("origin", origin)
+        .header("access-control-request-method", method);
+    request
+}
+
+fn cors() -> Cors {
+    Cors::new()
+        .allow_origin("https://example.com")
+        .allow_methods(vec![Method::GET, Method::PUT])
+        .allow_headers(vec!["content-type"])
+        .expose_headers(vec!["x-request-id"])
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(600))
+}
+
+#[test]
+fn test_app_preflight() {
+    let mut runner = test::runner(path!(@put "/posts").map(|| "updated"));
+    runner.cors(cors());
+
+    let mut request = preflight("/posts", "https://example.com", "PUT");
+    request.header("access-control-request-headers", "Content-Type");
+    let response = runner.perform(request).unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(response.body().is_empty());
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://example.com"
+    );
+    assert_eq!(headers["access-control-allow-methods"], "GET, PUT");
+    assert_eq!(headers["access-control-allow-headers"], "content-type");
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-max-age"], "600");
+
+    let response = runner
+        .perform(preflight("/posts", "https://evil.example.com", "PUT"))
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+
+    let response = runner
+        .perform(preflight("/posts", "https://example.com", "DELETE"))
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    let mut request = preflight("/posts", "https://example.com", "PUT");
+    request.header("access-control-request-headers", "x-custom");
+    let response = runner.perform(request).unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[test]
+fn test_app_actual_request() {
+    let mut runner = test::runner(path!(@get "/posts").map(|| "posts"));
+    runner.cors(cors());
+
+    let response = runner
+        .perform(
+            Request::get("/posts")
+                .header("origin", "https://example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.body(), "posts");
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "https://example.com"
+    );
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-expose-headers"], "x-request-id");
+    assert_eq!(headers["vary"], "origin");
+
+    // The headers are also added to the error responses.
+    let response = runner
+        .perform(
+            Request::get("/missing")
+                .header("origin", "https://example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    assert!(response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+
+    let response = runner
+        .perform(
+            Request::get("/posts")
+                .header("origin", "https://evil.example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+
+    // The requests without `Origin` are not affected.
+    let response = runner.perform("/posts").unwrap();
+    assert!(!response.headers().contains_key("vary"));
+}
+
+#[test]
+fn test_any_origin() {
+    let mut runner = test::runner(endpoint::value("Hello"));
+    runner.cors(Cors::new().allow_any_origin().allow_any_header());
+
+    let mut request = preflight("/", "https://example.com", "GET");
+    request.header("access-control-request-headers", "x-custom");
+    let response = runner.perform(request).unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    assert_eq!(
+        response.headers()["access-control-allow-headers"],
+        "x-custom"
+    );
+
+    let response = runner
+        .perform(
+            Request::get("/")
+                .header("origin", "https://example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    assert!(!response.headers().contains_key("vary"));
+}
+
+#[test]
+fn test_endpoint_cors() {
+    let mut runner = test::runner(
+        path!(@put "/api/posts")
+            .map(|| "updated")
+            .cors(cors())
+            .or(path!(@get "/public").map(|| "public")),
+    );
+
+    let response = runner
+        .perform(preflight("/api/posts", "https://example.com", "PUT"))
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://example.com"
+    );
+
+    // The preflight requests are routed by the requested method.
+    let response = runner
+        .perform(preflight("/api/posts", "https://example.com", "GET"))
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    // The endpoints outside of the subtree are not affected.
+    let response = runner
+        .perform(preflight("/public", "https://example.com", "GET"))
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = runner
+        .perform(
+            Request::put("/api/posts")
+                .header("origin", "https://example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.body(), "updated");
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://example.com"
+    );
+
+    let response = runner
+        .perform(
+            Request::get("/public")
+                .header("origin", "https://example.com")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "compression")]
+
+use finchers::endpoints::body;
+use finchers::error::Error;
+use finchers::service::decompression::Decompression;
+use finchers::test;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use http::Request;
+use std::io::Write;
+
+#[derive(Debug, PartialEq, serde::Deserialize)]
+struct Param {
+    text: String,
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(data: &[u8]) -> Vec<u8> {
+    let mut encoder = brotli::CompressorWriter::new(vec![], 4096, 5, 22);
+    encoder.write_all(data).unwrap();
+    encoder.into_inner()
+}
+
+fn status(result: Result<impl std::fmt::Debug, Error>) -> u16 {
+    result.unwrap_err().status_code().as_u16()
+}
+
+#[test]
+fn test_decompress_json() {
+    let mut runner = test::runner(body::json::<Param>());
+    runner.decompression(Decompression::new());
+
+    let json = br#"{ "text": "TRPL2" }"#;
+    for &(encoding, compress) in &[
+        ("gzip", gzip as fn(&[u8]) -> Vec<u8>),
+        ("deflate", deflate),
+        ("br", brotli),
+    ] {
+        let param: Param = runner
+            .apply(
+                Request::post("/")
+                    .header("content-type", "application/json")
+                    .header("content-encoding", encoding)
+                    .body(compress(json)),
+            )
+            .unwrap();
+        assert_eq!(param.text, "TRPL2", "content-encoding: {}", encoding);
+    }
+
+    // The requests without `Content-Encoding` are passed through.
+    let param: Param = runner
+        .apply(
+            Request::post("/")
+                .header("content-type", "application/json")
+                .body(&json[..]),
+        )
+        .unwrap();
+    assert_eq!(param.text, "TRPL2");
+}
+
+#[test]
+fn test_decompress_text_and_urlencoded() {
+    let mut runner = test::runner(body::text());
+    runner.decompression(Decompression::new());
+    let text: String = runner
+        .apply(
+            Request::post("/")
+                .header("content-encoding", "gzip")
+                .body(gzip(b"Hello, world")),
+        )
+        .unwrap();
+    assert_eq!(text, "Hello, world");
+
+    let mut runner = test::runner(body::urlencoded::<Param>());
+    runner.decompression(Decompression::new());
+    let param: Param = runner
+        .apply(
+            Request::post("/")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .header("content-encoding", "deflate")
+                .body(deflate(b"text=TRPL2")),
+        )
+        .unwrap();
+    assert_eq!(param.text, "TRPL2");
+}
+
+#[test]
+fn test_decompression_errors() {
+    let mut runner = test::runner(body::text());
+    runner.decompression(Decompression::new().max_size(1024));
+
+    // The decompressed body exceeds the limit.
+    assert_eq!(
+        status(
+            runner.apply::<String>(
+                Request::post("/")
+                    .header("content-encoding", "gzip")
+                    .body(gzip(&[b'a'; 1024 * 1024])),
+            )
+        ),
+        413
+    );
+
+    // The body is not compressed with the specified encoding.
+    assert_eq!(
+        status(
+            runner.apply::<String>(
+                Request::post("/")
+                    .header("content-encoding", "gzip")
+                    .body("Hello, world"),
+            )
+        ),
+        400
+    );
+
+    let response = runner
+        .perform(
+            Request::post("/")
+                .header("content-encoding", "compress")
+                .body("Hello, world"),
+        )
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 415);
+    assert_eq!(response.headers()["accept-encoding"], "gzip, deflate, br");
+}
+
+#[test]
+fn test_decompression_disabled() {
+    let mut runner = test::runner(body::receive_all());
+    let compressed = gzip(b"Hello, world");
+    let data: Vec<u8> = runner
+        .apply(
+            Request::post("/")
+                .header("content-encoding", "gzip")
+                .body(compressed.clone()),
+        )
+        .unwrap();
+    assert_eq!(data, compressed);
+}
@@ -0,0 +1,143 @@
+use finchers::error::Error;
+use finchers::path;
+use finchers::prelude::*;
+use finchers::service::{load_shed::LoadShed, App};
+use futures::{future, Future};
+use http::{Request, Response};
+use izanami_service::{MakeService, Service};
+use std::time::Duration;
+use tokio::runtime::current_thread::Runtime;
+
+fn get(uri: &str) -> Request<()> {
+    Request::get(uri).body(()).unwrap()
+}
+
+/// Polls the future only once, so that it holds the permits while the test is running.
+fn poll_once<F>(rt: &mut Runtime, mut future: F) -> F
+where
+    F: Future,
+{
+    rt.block_on(future::lazy(move || {
+        let _ = future.poll();
+        Ok::<_, ()>(future)
+    }))
+    .unwrap()
+}
+
+fn make_service<E>(
+    rt: &mut Runtime,
+    app: &App<E>,
+) -> <App<E> as MakeService<(), Request<()>>>::Service
+where
+    App<E>: MakeService<(), Request<()>>,
+    <App<E> as MakeService<(), Request<()>>>::MakeError: std::fmt::Debug,
+{
+    rt.block_on(MakeService::<(), Request<()>>::make_service(app, ()))
+        .unwrap()
+}
+
+fn status<Bd, E: std::fmt::Debug>(response: Result<Response<Bd>, E>) -> u16 {
+    response.unwrap().status().as_u16()
+}
+
+macro_rules! endpoint {
+    () => {
+        path!(@get "/pending")
+            .and_then(future::empty::<&'static str, Error>)
+            .or(path!(@get "/ready").map(|| "ready"))
+    };
+}
+
+#[test]
+fn test_max_in_flight() {
+    let mut rt = Runtime::new().unwrap();
+    let app = App::new(endpoint!()).load_shed(
+        LoadShed::new()
+            .max_in_flight(1)
+            .retry_after(Duration::from_millis(1500)),
+    );
+    let mut service = make_service(&mut rt, &app);
+
+    let pending = service.call(get("/pending"));
+
+    let response = rt.block_on(service.call(get("/ready"))).unwrap();
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(response.headers()["retry-after"], "2");
+
+    drop(pending);
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 200);
+}
+
+#[test]
+fn test_poll_ready() {
+    let mut rt = Runtime::new().unwrap();
+    let app = App::new(endpoint!()).load_shed(LoadShed::new().max_in_flight(1));
+    let mut service = make_service(&mut rt, &app);
+
+    // The readiness does not reserve any permits, even while the limit is reached.
+    assert!(service.poll_ready().unwrap().is_ready());
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 200);
+
+    let pending = service.call(get("/pending"));
+    assert!(service.poll_ready().unwrap().is_ready());
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 503);
+
+    drop(pending);
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 200);
+}
+
+#[test]
+fn test_max_in_flight_per_route() {
+    let mut rt = Runtime::new().unwrap();
+    let app = App::new(endpoint!()).load_shed(LoadShed::new().max_in_flight_per_route(1));
+    let mut service = make_service(&mut rt, &app);
+
+    let pending = poll_once(&mut rt, service.call(get("/pending")));
+
+    let response = rt.block_on(service.call(get("/pending"))).unwrap();
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    // The other routes are not affected.
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 200);
+
+    drop(pending);
+    let _pending = poll_once(&mut rt, service.call(get("/pending")));
+}
+
+#[test]
+fn test_route_limit() {
+    let mut rt = Runtime::new().unwrap();
+    let app = App::new(endpoint!()).load_shed(
+        LoadShed::new()
+            .max_in_flight_per_route(1)
+            .route("/pending", 2),
+    );
+    let mut service = make_service(&mut rt, &app);
+
+    let _pending1 = poll_once(&mut rt, service.call(get("/pending")));
+    let _pending2 = poll_once(&mut rt, service.call(get("/pending")));
+    assert_eq!(status(rt.block_on(service.call(get("/pending")))), 503);
+}
+
+#[test]
+fn test_concurrency_limit() {
+    let mut rt = Runtime::new().unwrap();
+    let app = App::new(
+        path!(@get "/pending")
+            .and_then(future::empty::<&'static str, Error>)
+            .concurrency_limit(1)
+            .retry_after(Duration::from_secs(3))
+            .or(path!(@get "/ready").map(|| "ready")),
+    );
+    let mut service = make_service(&mut rt, &app);
+
+    let pending = poll_once(&mut rt, service.call(get("/pending")));
+    let response = rt.block_on(service.call(get("/pending"))).unwrap();
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(response.headers()["retry-after"], "3");
+    assert_eq!(status(rt.block_on(service.call(get("/ready")))), 200);
+
+    drop(pending);
+    let _pending = poll_once(&mut rt, service.call(get("/pending")));
+}
@@ -0,0 +1,6 @@
+mod access_log;
+mod compression;
+mod cors;
+mod decompression;
+mod load_shed;
+mod tower;
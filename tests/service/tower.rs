@@ -0,0 +1,84 @@
+#![cfg(feature = "tower")]
+
+use finchers::prelude::*;
+use finchers::service::App;
+use futures::{Future, Poll};
+use http::{header::HeaderValue, Request, Response};
+use izanami_service::{MakeService, Service as _};
+use tokio::runtime::current_thread::Runtime;
+use tower_layer::Layer;
+use tower_service::Service;
+
+struct AddHeaderLayer(&'static str);
+
+struct AddHeader<S> {
+    inner: S,
+    value: &'static str,
+}
+
+impl<S, Req, Bd> Layer<S, Req> for AddHeaderLayer
+where
+    S: Service<Req, Response = Response<Bd>>,
+    S::Future: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type LayerError = ();
+    type Service = AddHeader<S>;
+
+    fn layer(&self, inner: S) -> Result<Self::Service, Self::LayerError> {
+        Ok(AddHeader {
+            inner,
+            value: self.0,
+        })
+    }
+}
+
+impl<S, Req, Bd> Service<Req> for AddHeader<S>
+where
+    S: Service<Req, Response = Response<Bd>>,
+    S::Future: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, request: Req) -> Self::Future {
+        let value = self.value;
+        Box::new(self.inner.call(request).map(move |mut response| {
+            response
+                .headers_mut()
+                .append("x-layer", HeaderValue::from_static(value));
+            response
+        }))
+    }
+}
+
+#[test]
+fn test_app_as_tower_service() {
+    let mut rt = Runtime::new().unwrap();
+    let mut app = App::new(endpoint::value("Hello"));
+
+    let response = rt.block_on(app.call(Request::new(()))).unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test]
+fn test_with_tower_layer() {
+    let mut rt = Runtime::new().unwrap();
+    let layered = App::new(endpoint::value("Hello"))
+        .with_tower_layer(AddHeaderLayer("inner"))
+        .with_tower_layer(AddHeaderLayer("outer"));
+
+    let mut service = rt
+        .block_on(MakeService::<(), Request<()>>::make_service(&layered, ()))
+        .unwrap();
+    let response = rt.block_on(service.call(Request::new(()))).unwrap();
+
+    let values: Vec<_> = response.headers().get_all("x-layer").iter().collect();
+    assert_eq!(values, vec!["inner", "outer"]);
+}
@@ -1,5 +1,8 @@
 mod endpoint;
 mod endpoints;
+mod error;
+mod server;
+mod service;
 
 #[test]
 fn version_sync() {